@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
 use std::time::Instant;
 
 #[allow(unused_macros)]
@@ -58,9 +59,135 @@ fn part1(data: &[Vec<usize>]) -> Result<usize> {
     Ok(result)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reason {
+    DirectionChange,
+    StepTooLarge,
+    StepTooSmall,
+    EqualLevels,
+}
+
+// the first pair of levels (index, index + 1) that breaks the rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Violation {
+    index: usize,
+    reason: Reason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Verdict {
+    Safe,
+    Fixable(Vec<usize>, Violation),
+    Unsafe(Violation),
+}
+
+#[derive(Debug, Clone)]
+struct SafetyRules {
+    steps: RangeInclusive<usize>,
+    removals: usize,
+}
+
+impl Default for SafetyRules {
+    fn default() -> Self {
+        SafetyRules {
+            steps: 1..=3,
+            removals: 1,
+        }
+    }
+}
+
+impl SafetyRules {
+    fn step_ok(&self, a: usize, b: usize, increasing: bool) -> bool {
+        (a < b) == increasing && a != b && self.steps.contains(&a.abs_diff(b))
+    }
+
+    fn first_violation(&self, row: &[usize]) -> Option<Violation> {
+        let increasing = row.len() > 1 && row[0] < row[1];
+        (0..row.len().saturating_sub(1)).find_map(|index| {
+            let (a, b) = (row[index], row[index + 1]);
+            let reason = if a == b {
+                Reason::EqualLevels
+            } else if (a < b) != increasing {
+                Reason::DirectionChange
+            } else if a.abs_diff(b) > *self.steps.end() {
+                Reason::StepTooLarge
+            } else if a.abs_diff(b) < *self.steps.start() {
+                Reason::StepTooSmall
+            } else {
+                return None;
+            };
+            Some(Violation { index, reason })
+        })
+    }
+
+    // O(n * removals): fewest[i] is the least number of levels removed so far when
+    // level i is kept as the latest one, only the previous `removals + 1` levels can precede it
+    fn fewest_removals(&self, row: &[usize], increasing: bool) -> Option<Vec<usize>> {
+        let n = row.len();
+        let k = self.removals;
+        let mut fewest = vec![usize::MAX; n];
+        let mut prev = vec![None; n];
+        for i in 0..n {
+            if i <= k {
+                fewest[i] = i;
+            }
+            for j in i.saturating_sub(k + 1)..i {
+                if fewest[j] == usize::MAX || !self.step_ok(row[j], row[i], increasing) {
+                    continue;
+                }
+                if fewest[j] + i - j - 1 <= fewest[i] {
+                    fewest[i] = fewest[j] + i - j - 1;
+                    prev[i] = Some(j);
+                }
+            }
+        }
+        let (last, removed) = (0..n)
+            .filter(|&i| fewest[i] != usize::MAX)
+            .map(|i| (i, fewest[i] + n - 1 - i))
+            .min_by_key(|&(_, removed)| removed)?;
+        if removed > k {
+            return None;
+        }
+        let mut kept = vec![false; n];
+        let mut cur = Some(last);
+        while let Some(i) = cur {
+            kept[i] = true;
+            cur = prev[i];
+        }
+        Some((0..n).filter(|&i| !kept[i]).collect())
+    }
+
+    fn analyze(&self, row: &[usize]) -> Verdict {
+        let Some(violation) = self.first_violation(row) else {
+            return Verdict::Safe;
+        };
+        let fix = [true, false]
+            .into_iter()
+            .filter_map(|increasing| self.fewest_removals(row, increasing))
+            .min_by_key(|removed| removed.len());
+        match fix {
+            Some(removed) => Verdict::Fixable(removed, violation),
+            None => Verdict::Unsafe(violation),
+        }
+    }
+}
+
 fn part2(data: &[Vec<usize>]) -> Result<usize> {
     let _start = Instant::now();
 
+    let rules = SafetyRules::default();
+    let result = data
+        .iter()
+        .filter(|row| !matches!(rules.analyze(row), Verdict::Unsafe(_)))
+        .count();
+    println!("part2: {result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+fn part2_bruteforce(data: &[Vec<usize>]) -> Result<usize> {
+    let _start = Instant::now();
+
     let mut result = 0;
     for row in data {
         if is_safe(row, row.len()) {
@@ -74,7 +201,7 @@ fn part2(data: &[Vec<usize>]) -> Result<usize> {
             }
         }
     }
-    println!("part2: {result}");
+    println!("part2 with bruteforce: {result}");
     writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
     Ok(result)
 }
@@ -86,6 +213,7 @@ fn main() -> Result<()> {
     let data = parse_input(&input);
     part1(&data)?;
     part2(&data)?;
+    part2_bruteforce(&data)?;
     Ok(())
 }
 
@@ -100,6 +228,36 @@ fn example_input() {
     let data = parse_input(input);
     assert_eq!(part1(&data).unwrap(), 2);
     assert_eq!(part2(&data).unwrap(), 4);
+    assert_eq!(part2_bruteforce(&data).unwrap(), 4);
+
+    use Reason::*;
+    let rules = SafetyRules::default();
+    let verdicts: Vec<_> = data.iter().map(|row| rules.analyze(row)).collect();
+    let violation = |index, reason| Violation { index, reason };
+    assert_eq!(
+        verdicts,
+        vec![
+            Verdict::Safe,
+            Verdict::Unsafe(violation(1, StepTooLarge)),
+            Verdict::Unsafe(violation(2, StepTooLarge)),
+            Verdict::Fixable(vec![1], violation(1, DirectionChange)),
+            Verdict::Fixable(vec![2], violation(2, EqualLevels)),
+            Verdict::Safe,
+        ]
+    );
+
+    let rules = SafetyRules {
+        steps: 2..=5,
+        removals: 2,
+    };
+    assert_eq!(
+        rules.analyze(&data[1]),
+        Verdict::Fixable(vec![0, 3], violation(0, StepTooSmall))
+    );
+    assert_eq!(
+        rules.analyze(&data[2]),
+        Verdict::Fixable(vec![1, 4], violation(1, StepTooSmall))
+    );
 }
 
 #[test]
@@ -108,4 +266,5 @@ fn real_input() {
     let data = parse_input(input);
     assert_eq!(part1(&data).unwrap(), 510);
     assert_eq!(part2(&data).unwrap(), 553);
+    assert_eq!(part2_bruteforce(&data).unwrap(), 553);
 }
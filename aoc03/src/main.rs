@@ -1,6 +1,8 @@
 use regex::Regex;
+use std::collections::VecDeque;
 use std::error::Error;
-use std::io::{self, Read, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::Range;
 use std::str::FromStr;
 use std::time::Instant;
 
@@ -24,12 +26,12 @@ impl FromStr for Instruction {
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         let s = s.trim();
         if let Some(s) = s.strip_prefix("mul(") {
-            if let Some(s) = s.strip_suffix(")") {
-                if let Some((l, r)) = s.split_once(',') {
-                    let l: isize = l.trim().parse()?;
-                    let r: isize = r.trim().parse()?;
-                    return Ok(Instruction::Mul(l, r));
-                }
+            if let Some(s) = s.strip_suffix(")")
+                && let Some((l, r)) = s.split_once(',')
+            {
+                let l: isize = l.trim().parse()?;
+                let r: isize = r.trim().parse()?;
+                return Ok(Instruction::Mul(l, r));
             }
         } else if s == "do()" {
             return Ok(Instruction::Do);
@@ -87,9 +89,202 @@ fn part2(instrs: &[Instruction]) -> Result<isize> {
     Ok(result)
 }
 
+#[derive(Debug, Default)]
+struct Machine {
+    disabled: bool,
+    acc: isize,
+}
+
+type Semantics = fn(&mut Machine, &[isize]);
+
+struct InstructionSpec {
+    name: String,
+    arity: usize,
+    max_digits: usize,
+    semantics: Semantics,
+}
+
+impl InstructionSpec {
+    fn max_len(&self) -> usize {
+        self.name.len() + 2 + self.arity * (self.max_digits + 1)
+    }
+
+    // `bytes` always holds at least `max_len` bytes unless the input ends first
+    fn match_at(&self, bytes: &[u8]) -> Option<(usize, Vec<isize>)> {
+        let mut rest = bytes
+            .strip_prefix(self.name.as_bytes())?
+            .strip_prefix(b"(")?;
+        let mut args = Vec::with_capacity(self.arity);
+        for i in 0..self.arity {
+            if i > 0 {
+                rest = rest.strip_prefix(b",")?;
+            }
+            let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+            if digits == 0 || digits > self.max_digits {
+                return None;
+            }
+            args.push(std::str::from_utf8(&rest[..digits]).ok()?.parse().ok()?);
+            rest = &rest[digits..];
+        }
+        rest = rest.strip_prefix(b")")?;
+        Some((bytes.len() - rest.len(), args))
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    specs: Vec<InstructionSpec>,
+}
+
+impl Registry {
+    fn register(&mut self, name: &str, arity: usize, max_digits: usize, semantics: Semantics) {
+        self.specs.push(InstructionSpec {
+            name: name.to_string(),
+            arity,
+            max_digits,
+            semantics,
+        });
+    }
+
+    fn part1() -> Self {
+        let mut registry = Registry::default();
+        registry.register("mul", 2, 3, |m, args| m.acc += args[0] * args[1]);
+        registry
+    }
+
+    fn part2() -> Self {
+        let mut registry = Registry::default();
+        registry.register("mul", 2, 3, |m, args| {
+            if !m.disabled {
+                m.acc += args[0] * args[1]
+            }
+        });
+        registry.register("do", 0, 0, |m, _| m.disabled = false);
+        registry.register("don't", 0, 0, |m, _| m.disabled = true);
+        registry
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Trace {
+    // byte span, instruction name, arguments, whether the machine was enabled
+    Accepted(Range<usize>, String, Vec<isize>, bool),
+    Skipped(Range<usize>),
+}
+
+// keeps only a window as long as the longest instruction, so the input never has to be
+// loaded at once; the trace is optional for the same reason
+fn interpret<R: Read>(
+    registry: &Registry,
+    reader: R,
+    trace: bool,
+) -> Result<(Machine, Vec<Trace>)> {
+    let mut reader = BufReader::new(reader);
+    let lookahead = registry
+        .specs
+        .iter()
+        .map(|s| s.max_len())
+        .max()
+        .unwrap_or(1);
+    let mut window: VecDeque<u8> = VecDeque::with_capacity(lookahead * 2);
+    let mut machine = Machine::default();
+    let mut events = vec![];
+    let (mut offset, mut skipped_from) = (0, None);
+    let mut eof = false;
+
+    loop {
+        while !eof && window.len() < lookahead {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                eof = true;
+                break;
+            }
+            let take = buf.len().min(lookahead * 2 - window.len());
+            window.extend(&buf[..take]);
+            reader.consume(take);
+        }
+        if window.is_empty() {
+            break;
+        }
+        let bytes = window.make_contiguous();
+        let found = registry
+            .specs
+            .iter()
+            .filter_map(|spec| spec.match_at(bytes).map(|m| (spec, m)))
+            .max_by_key(|(_, (len, _))| *len);
+        let consumed = match found {
+            Some((spec, (len, args))) => {
+                if trace {
+                    if let Some(from) = skipped_from.take() {
+                        events.push(Trace::Skipped(from..offset));
+                    }
+                    events.push(Trace::Accepted(
+                        offset..offset + len,
+                        spec.name.clone(),
+                        args.clone(),
+                        !machine.disabled,
+                    ));
+                }
+                (spec.semantics)(&mut machine, &args);
+                len
+            }
+            None => {
+                skipped_from.get_or_insert(offset);
+                1
+            }
+        };
+        window.drain(..consumed);
+        offset += consumed;
+    }
+    if trace && let Some(from) = skipped_from {
+        events.push(Trace::Skipped(from..offset));
+    }
+    Ok((machine, events))
+}
+
+fn part1_interpreter<R: Read>(reader: R) -> Result<isize> {
+    let _start = Instant::now();
+
+    let result = interpret(&Registry::part1(), reader, false)?.0.acc;
+
+    println!("part1 with interpreter: {result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+fn part2_interpreter<R: Read>(reader: R) -> Result<isize> {
+    let _start = Instant::now();
+
+    let result = interpret(&Registry::part2(), reader, false)?.0.acc;
+
+    println!("part2 with interpreter: {result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+// keeps a copy of everything read, so a stream can be replayed once it has been consumed
+struct Tee<R> {
+    inner: R,
+    copy: Vec<u8>,
+}
+
+impl<R: Read> Read for Tee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.copy.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
 fn main() -> Result<()> {
-    let mut input = String::new();
-    io::stdin().read_to_string(&mut input)?;
+    // the interpreter streams straight from stdin, the other solvers get the copy
+    let mut stdin = Tee {
+        inner: io::stdin().lock(),
+        copy: vec![],
+    };
+    part1_interpreter(&mut stdin)?;
+    let input = String::from_utf8(stdin.copy)?;
+    part2_interpreter(input.as_bytes())?;
 
     let instrs = parse_input(input)?;
 
@@ -105,13 +300,53 @@ fn example_input() {
 
     assert_eq!(part1(&instrs).unwrap(), 161);
     assert_eq!(part2(&instrs).unwrap(), 48);
+    assert_eq!(part1_interpreter(input.as_bytes()).unwrap(), 161);
+    assert_eq!(part2_interpreter(input.as_bytes()).unwrap(), 48);
+
+    let mut tee = Tee {
+        inner: input.as_bytes(),
+        copy: vec![],
+    };
+    assert_eq!(part1_interpreter(&mut tee).unwrap(), 161);
+    assert_eq!(tee.copy, input.as_bytes());
+}
+
+#[test]
+fn interpreter_trace() {
+    let input = "xmul(2,4)don't()mul(1,1)mul(1234,5)do()";
+    // a tiny buffer forces instructions to straddle reads
+    let reader = BufReader::with_capacity(3, input.as_bytes());
+    let (machine, trace) = interpret(&Registry::part2(), reader, true).unwrap();
+    assert_eq!(machine.acc, 8);
+    assert_eq!(
+        trace,
+        vec![
+            Trace::Skipped(0..1),
+            Trace::Accepted(1..9, "mul".to_string(), vec![2, 4], true),
+            Trace::Accepted(9..16, "don't".to_string(), vec![], true),
+            Trace::Accepted(16..24, "mul".to_string(), vec![1, 1], false),
+            Trace::Skipped(24..35),
+            Trace::Accepted(35..39, "do".to_string(), vec![], false),
+        ]
+    );
+
+    let mut registry = Registry::part2();
+    registry.register("add", 3, 2, |m, args| m.acc += args.iter().sum::<isize>());
+    let (machine, _) = interpret(
+        &registry,
+        "add(1,2,3)add(1,2)mul(3,3)add(10,20,300)".as_bytes(),
+        false,
+    )
+    .unwrap();
+    assert_eq!(machine.acc, 15);
 }
 
 #[test]
 fn real_input() {
     let input = std::fs::read_to_string("input/input.txt").unwrap();
-    let instrs = parse_input(input).unwrap();
+    let instrs = parse_input(&input).unwrap();
 
     assert_eq!(part1(&instrs).unwrap(), 178886550);
     assert_eq!(part2(&instrs).unwrap(), 87163705);
+    assert_eq!(part2_interpreter(input.as_bytes()).unwrap(), 87163705);
 }
@@ -99,6 +99,151 @@ fn part2(grid: &Grid) -> Result<usize> {
     Ok(result)
 }
 
+const DIRECTIONS: [(isize, isize); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+fn step(
+    grid: &Grid,
+    (i, j): (usize, usize),
+    (di, dj): (isize, isize),
+    n: isize,
+    wrap: bool,
+) -> Option<(usize, usize)> {
+    let (h, w) = (grid.len() as isize, grid[0].len() as isize);
+    let (mut x, mut y) = (i as isize + di * n, j as isize + dj * n);
+    if wrap {
+        (x, y) = (x.rem_euclid(h), y.rem_euclid(w));
+    }
+    ((0..h).contains(&x) && (0..w).contains(&y)).then_some((x as usize, y as usize))
+}
+
+// every (start, direction) where `word` can be read, optionally wrapping around the edges
+fn search_word(
+    grid: &Grid,
+    word: &str,
+    directions: &[(isize, isize)],
+    wrap: bool,
+) -> Vec<((usize, usize), (isize, isize))> {
+    let word: Vec<_> = word.chars().collect();
+    let mut matches = vec![];
+    for i in 0..grid.len() {
+        for j in 0..grid[0].len() {
+            if word.first() != Some(&grid[i][j]) {
+                continue;
+            }
+            for &d in directions {
+                let found = word.iter().enumerate().all(|(n, &c)| {
+                    step(grid, (i, j), d, n as isize, wrap).is_some_and(|(x, y)| grid[x][y] == c)
+                });
+                if found {
+                    matches.push(((i, j), d));
+                }
+            }
+        }
+    }
+    matches
+}
+
+// small pattern grid, '.' matches any letter
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mask {
+    cells: Vec<Vec<char>>,
+}
+
+impl Mask {
+    fn new(pattern: &str) -> Result<Self> {
+        let cells = parse_input(pattern);
+        if cells.is_empty() || cells[0].is_empty() {
+            return err!("empty mask");
+        }
+        if let Some(i) = cells.iter().position(|row| row.len() != cells[0].len()) {
+            return err!("mask row {i} is not {} cells wide", cells[0].len());
+        }
+        Ok(Mask { cells })
+    }
+
+    fn rotate(&self) -> Self {
+        let (h, w) = (self.cells.len(), self.cells[0].len());
+        Mask {
+            cells: (0..w)
+                .map(|j| (0..h).rev().map(|i| self.cells[i][j]).collect())
+                .collect(),
+        }
+    }
+
+    fn rotations(&self) -> Vec<Mask> {
+        let mut rotations = vec![self.clone()];
+        for _ in 0..3 {
+            let next = rotations.last().unwrap().rotate();
+            if !rotations.contains(&next) {
+                rotations.push(next);
+            }
+        }
+        rotations
+    }
+
+    fn matches_at(&self, grid: &Grid, start: (usize, usize), wrap: bool) -> bool {
+        self.cells.iter().enumerate().all(|(di, row)| {
+            row.iter().enumerate().all(|(dj, &c)| {
+                c == '.'
+                    || step(grid, start, (di as isize, 0), 1, wrap)
+                        .and_then(|cur| step(grid, cur, (0, dj as isize), 1, wrap))
+                        .is_some_and(|(x, y)| grid[x][y] == c)
+            })
+        })
+    }
+}
+
+// every place a mask matches in any of its rotations, as (mask index, quarter turns,
+// top left corner)
+fn search_masks(grid: &Grid, masks: &[Mask], wrap: bool) -> Vec<(usize, usize, (usize, usize))> {
+    let mut matches = vec![];
+    for (k, mask) in masks.iter().enumerate() {
+        for (turns, mask) in mask.rotations().iter().enumerate() {
+            let (h, w) = (mask.cells.len(), mask.cells[0].len());
+            for i in 0..grid.len() {
+                for j in 0..grid[0].len() {
+                    let fits = wrap || (i + h <= grid.len() && j + w <= grid[0].len());
+                    if fits && mask.matches_at(grid, (i, j), wrap) {
+                        matches.push((k, turns, (i, j)));
+                    }
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn part1_pattern(grid: &Grid) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = search_word(grid, "XMAS", &DIRECTIONS, false).len();
+
+    println!("part1 with pattern engine: {result}");
+
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+fn part2_pattern(grid: &Grid) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = search_masks(grid, &[Mask::new("M.S\n.A.\nM.S")?], false).len();
+
+    println!("part2 with pattern engine: {result}");
+
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
@@ -106,6 +251,8 @@ fn main() -> Result<()> {
     let grid = parse_input(input);
     part1(&grid)?;
     part2(&grid)?;
+    part1_pattern(&grid)?;
+    part2_pattern(&grid)?;
     Ok(())
 }
 
@@ -124,6 +271,8 @@ MXMXAXMASX";
     let grid = parse_input(input);
     assert_eq!(part1(&grid).unwrap(), 18);
     assert_eq!(part2(&grid).unwrap(), 9);
+    assert_eq!(part1_pattern(&grid).unwrap(), 18);
+    assert_eq!(part2_pattern(&grid).unwrap(), 9);
     assert_eq!(1, 1);
 }
 
@@ -133,5 +282,46 @@ fn real_input() {
     let grid = parse_input(input);
     assert_eq!(part1(&grid).unwrap(), 2493);
     assert_eq!(part2(&grid).unwrap(), 1890);
+    assert_eq!(part1_pattern(&grid).unwrap(), 2493);
+    assert_eq!(part2_pattern(&grid).unwrap(), 1890);
     assert_eq!(2, 2);
 }
+
+#[test]
+fn pattern_engine() -> Result<()> {
+    let grid = parse_input("SAMX\nXMAS\nAMAX\nASXM");
+    assert_eq!(
+        search_word(&grid, "XMAS", &DIRECTIONS[..1], false),
+        vec![((1, 0), (0, 1))]
+    );
+    assert_eq!(
+        search_word(&grid, "XMAS", &[(0, -1)], false),
+        vec![((0, 3), (0, -1))]
+    );
+    // the last row only reads XMAS when wrapping around to its first two letters
+    assert_eq!(
+        search_word(&grid, "XMAS", &[(0, 1)], true),
+        vec![((1, 0), (0, 1)), ((3, 2), (0, 1))]
+    );
+
+    let plus = Mask::new(".M.\nMAS\n.S.")?;
+    assert_eq!(plus.rotations().len(), 4);
+    let grid = parse_input("XMXX\nMASX\nXSXX");
+    assert_eq!(
+        search_masks(&grid, std::slice::from_ref(&plus), false),
+        vec![(0, 0, (0, 0))]
+    );
+    // hits say which mask matched and how far it was turned
+    let x_mas = Mask::new("M.S\n.A.\nM.S")?;
+    let grid = parse_input("SXMXMX\nXAXSAM\nSXMXSX");
+    assert_eq!(
+        search_masks(&grid, &[plus, x_mas], false),
+        vec![(0, 1, (0, 3)), (1, 2, (0, 0))]
+    );
+    assert_eq!(Mask::new("A.A\n.A.\nA.A")?.rotations().len(), 1);
+
+    assert!(Mask::new("").is_err());
+    assert!(Mask::new("\n\n").is_err());
+    assert!(Mask::new("M.S\n.A\nM.S").is_err());
+    Ok(())
+}
@@ -113,12 +113,159 @@ fn part1_without_topological_sorting(rules: &OrderingRules, updates: &[Update])
     Ok(result)
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum RuleViolation {
+    // pages along the cycle, the first page is repeated at the end
+    Cycle(Vec<usize>),
+    // two pages that could come next in either order
+    Ambiguous(usize, usize),
+    // a page listed more than once in the update
+    Duplicate(usize),
+}
+
+impl std::fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleViolation::Cycle(chain) => {
+                let chain: Vec<_> = chain.iter().map(|p| p.to_string()).collect();
+                write!(f, "ordering rules form a cycle: {}", chain.join(" -> "))
+            }
+            RuleViolation::Ambiguous(a, b) => write!(f, "no rule decides the order of {a} and {b}"),
+            RuleViolation::Duplicate(p) => write!(f, "page {p} is listed more than once"),
+        }
+    }
+}
+
+impl Error for RuleViolation {}
+
+fn find_cycle(rules: &OrderingRules, pages: &HashSet<usize>) -> Option<Vec<usize>> {
+    fn dfs(
+        cur: usize,
+        rules: &OrderingRules,
+        pages: &HashSet<usize>,
+        done: &mut HashSet<usize>,
+        path: &mut Vec<usize>,
+    ) -> Option<Vec<usize>> {
+        if let Some(start) = path.iter().position(|&p| p == cur) {
+            let mut chain = path[start..].to_vec();
+            chain.push(cur);
+            return Some(chain);
+        }
+        if done.contains(&cur) {
+            return None;
+        }
+        path.push(cur);
+        let mut after: Vec<_> = rules
+            .get(&cur)
+            .into_iter()
+            .flatten()
+            .filter(|p| pages.contains(p))
+            .cloned()
+            .collect();
+        after.sort();
+        for next in after {
+            if let Some(chain) = dfs(next, rules, pages, done, path) {
+                return Some(chain);
+            }
+        }
+        path.pop();
+        done.insert(cur);
+        None
+    }
+
+    let mut sorted: Vec<_> = pages.iter().cloned().collect();
+    sorted.sort();
+    let mut done = HashSet::new();
+    sorted
+        .into_iter()
+        .find_map(|page| dfs(page, rules, pages, &mut done, &mut vec![]))
+}
+
+fn all_pages(rules: &OrderingRules) -> HashSet<usize> {
+    rules
+        .iter()
+        .flat_map(|(&a, after)| std::iter::once(a).chain(after.iter().cloned()))
+        .collect()
+}
+
+fn duplicate_page(update: &Update) -> Option<usize> {
+    let mut seen = HashSet::new();
+    update.iter().find(|&&p| !seen.insert(p)).cloned()
+}
+
+// the only order the rules allow for this update, or why there is none
+fn check_update(
+    rules: &OrderingRules,
+    update: &Update,
+) -> std::result::Result<Update, RuleViolation> {
+    if let Some(page) = duplicate_page(update) {
+        return Err(RuleViolation::Duplicate(page));
+    }
+    let pages: HashSet<_> = update.iter().cloned().collect();
+    if let Some(chain) = find_cycle(rules, &pages) {
+        return Err(RuleViolation::Cycle(chain));
+    }
+    let mut before: HashMap<usize, usize> = pages.iter().map(|&p| (p, 0)).collect();
+    for &page in &pages {
+        for next in rules
+            .get(&page)
+            .into_iter()
+            .flatten()
+            .filter(|p| pages.contains(p))
+        {
+            *before.get_mut(next).unwrap() += 1;
+        }
+    }
+    let mut order = Vec::with_capacity(pages.len());
+    while order.len() < pages.len() {
+        let mut ready: Vec<_> = before
+            .iter()
+            .filter(|&(_, &n)| n == 0)
+            .map(|(&p, _)| p)
+            .collect();
+        ready.sort();
+        // the cycle check above guarantees at least one page is ready
+        if ready.len() > 1 {
+            return Err(RuleViolation::Ambiguous(ready[0], ready[1]));
+        }
+        let page = ready[0];
+        before.remove(&page);
+        for next in rules.get(&page).into_iter().flatten() {
+            if let Some(n) = before.get_mut(next) {
+                *n -= 1;
+            }
+        }
+        order.push(page);
+    }
+    Ok(order)
+}
+
 fn part2(rules: &OrderingRules, updates: &[Update]) -> Result<usize> {
     let _start = Instant::now();
 
     let mut result = 0;
 
     for update in updates {
+        let order = check_update(rules, update).map_err(|e| format!("update {update:?}: {e}"))?;
+        if order != *update {
+            result += order[order.len() / 2];
+        }
+    }
+
+    println!("part2:{result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+fn part2_sort(rules: &OrderingRules, updates: &[Update]) -> Result<usize> {
+    let _start = Instant::now();
+
+    let mut result = 0;
+
+    for update in updates {
+        if let Some(page) = duplicate_page(update) {
+            return err!("update {update:?}: {}", RuleViolation::Duplicate(page));
+        }
         let mut update = update.clone();
         let ordering_rules = build_ordering_rules(rules, &update);
         if !update.is_sorted_by(|&a, &b| find(&ordering_rules, a, b)) {
@@ -135,7 +282,7 @@ fn part2(rules: &OrderingRules, updates: &[Update]) -> Result<usize> {
         }
     }
 
-    println!("part2:{result}");
+    println!("part2 with sorting:{result}");
     writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
     Ok(result)
 }
@@ -171,9 +318,13 @@ fn main() -> Result<()> {
     io::stdin().read_to_string(&mut input)?;
 
     let (rules, updates) = parse_input(input);
+    if let Some(chain) = find_cycle(&rules, &all_pages(&rules)) {
+        println!("global {}", RuleViolation::Cycle(chain));
+    }
     part1(&rules, &updates)?;
     part1_without_topological_sorting(&rules, &updates)?;
     part2(&rules, &updates)?;
+    part2_sort(&rules, &updates)?;
     part2_without_topological_sorting(&rules, &updates)?;
     Ok(())
 }
@@ -215,6 +366,8 @@ fn example_input() {
         143
     );
     assert_eq!(part2(&rules, &updates).unwrap(), 123);
+    assert_eq!(part2_sort(&rules, &updates).unwrap(), 123);
+    assert_eq!(find_cycle(&rules, &all_pages(&rules)), None);
     assert_eq!(
         part2_without_topological_sorting(&rules, &updates).unwrap(),
         123
@@ -232,8 +385,52 @@ fn real_input() {
         5129
     );
     assert_eq!(part2(&rules, &updates).unwrap(), 4077);
+    assert_eq!(part2_sort(&rules, &updates).unwrap(), 4077);
     assert_eq!(
         part2_without_topological_sorting(&rules, &updates).unwrap(),
         4077
     );
 }
+
+#[test]
+fn rule_violations() {
+    let (rules, updates) = parse_input("1|2\n2|3\n3|1\n4|5\n4|6\n\n1,2,3\n4,5,6\n1,2\n6,4");
+    assert_eq!(
+        find_cycle(&rules, &all_pages(&rules)),
+        Some(vec![1, 2, 3, 1])
+    );
+    assert_eq!(
+        check_update(&rules, &updates[0]),
+        Err(RuleViolation::Cycle(vec![1, 2, 3, 1]))
+    );
+    assert_eq!(
+        check_update(&rules, &updates[1]),
+        Err(RuleViolation::Ambiguous(5, 6))
+    );
+    assert_eq!(check_update(&rules, &updates[2]), Ok(vec![1, 2]));
+    assert_eq!(check_update(&rules, &updates[3]), Ok(vec![4, 6]));
+
+    assert_eq!(part2(&rules, &updates[2..]).unwrap(), 6);
+    let e = part2(&rules, &updates).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "update [1, 2, 3]: ordering rules form a cycle: 1 -> 2 -> 3 -> 1"
+    );
+
+    // a repeated page would otherwise shorten the order and read as unsorted
+    let (rules, updates) = parse_input("4|5\n5|6\n\n4,5,5,6");
+    assert_eq!(
+        check_update(&rules, &updates[0]),
+        Err(RuleViolation::Duplicate(5))
+    );
+    let e = part2(&rules, &updates).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "update [4, 5, 5, 6]: page 5 is listed more than once"
+    );
+    let e = part2_sort(&rules, &updates).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "update [4, 5, 5, 6]: page 5 is listed more than once"
+    );
+}
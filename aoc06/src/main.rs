@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::io::{self, Read, Write};
 use std::time::Instant;
@@ -21,6 +21,19 @@ enum Direction {
 }
 
 impl Direction {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn delta(self) -> Coord {
+        match self {
+            Direction::Up => (-1, 0),
+            Direction::Down => (1, 0),
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+        }
+    }
+
     fn turn(self) -> Self {
         use Direction::*;
         match self {
@@ -206,6 +219,240 @@ fn part2(grid: &Grid, guard: &Guard) -> Result<usize> {
     Ok(result)
 }
 
+// for every (cell, facing): the cell where the guard stops in front of the next obstacle,
+// or None when she walks off the map
+struct JumpTable {
+    width: usize,
+    stops: [Vec<Option<Coord>>; 4],
+}
+
+impl JumpTable {
+    fn new(grid: &Grid) -> Self {
+        let (height, width) = (grid.len(), grid[0].len());
+        let mut stops = [(); 4].map(|_| vec![None; height * width]);
+        for facing in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            let (dx, dy) = facing.delta();
+            // walk against the facing so the stop of the cell ahead is always known
+            let rows: Vec<_> = if dx > 0 {
+                (0..height).rev().collect()
+            } else {
+                (0..height).collect()
+            };
+            let cols: Vec<_> = if dy > 0 {
+                (0..width).rev().collect()
+            } else {
+                (0..width).collect()
+            };
+            for &x in &rows {
+                for &y in &cols {
+                    let ahead = (x as i32 + dx, y as i32 + dy);
+                    stops[facing.index()][x * width + y] = match grid_at(grid, ahead) {
+                        None => None,
+                        Some('#') => Some((x as i32, y as i32)),
+                        Some(_) => {
+                            stops[facing.index()][ahead.0 as usize * width + ahead.1 as usize]
+                        }
+                    };
+                }
+            }
+        }
+        JumpTable { width, stops }
+    }
+
+    fn state_index(&self, guard: &Guard) -> usize {
+        (guard.coord.0 as usize * self.width + guard.coord.1 as usize) * 4 + guard.facing.index()
+    }
+
+    // next turning point, with `extra` patched into its row and column on the fly
+    fn jump(&self, guard: &Guard, extra: Option<Coord>) -> Option<Coord> {
+        let (x, y) = guard.coord;
+        let stop = self.stops[guard.facing.index()][x as usize * self.width + y as usize];
+        let Some((ox, oy)) = extra else {
+            return stop;
+        };
+        let (dx, dy) = guard.facing.delta();
+        let ahead = (ox - x) * dx + (oy - y) * dy;
+        let in_line = if dx == 0 { ox == x } else { oy == y };
+        if !in_line || ahead < 1 {
+            return stop;
+        }
+        match stop {
+            Some((sx, sy)) if (sx - x) * dx + (sy - y) * dy < ahead => stop,
+            _ => Some((ox - dx, oy - dy)),
+        }
+    }
+
+    fn loops(&self, guard: &Guard, extra: Option<Coord>, seen: &mut [usize], stamp: usize) -> bool {
+        let mut guard = *guard;
+        while let Some(stop) = self.jump(&guard, extra) {
+            guard.coord = stop;
+            guard.turn();
+            let state = self.state_index(&guard);
+            if seen[state] == stamp {
+                return true;
+            }
+            seen[state] = stamp;
+        }
+        false
+    }
+}
+
+fn loop_obstacles(grid: &Grid, guard: &Guard) -> Vec<Coord> {
+    let table = JumpTable::new(grid);
+    let mut seen = vec![usize::MAX; grid.len() * grid[0].len() * 4];
+    let mut candidates: Vec<_> = patrol_route(grid, guard)
+        .into_iter()
+        .filter(|&c| c != guard.coord)
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .enumerate()
+        .filter(|&(stamp, obstacle)| table.loops(guard, Some(obstacle), &mut seen, stamp))
+        .map(|(_, obstacle)| obstacle)
+        .collect()
+}
+
+fn part2_jump_table(grid: &Grid, guard: &Guard) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = loop_obstacles(grid, guard).len();
+
+    println!("part2 with jump table: {result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+struct PatrolTrace {
+    states: Vec<Guard>,
+    // index of the state the guard came back to, the last state is the repeat
+    repeated: Option<usize>,
+}
+
+fn trace_patrol(grid: &Grid, guard: &Guard, obstacle: Option<Coord>) -> PatrolTrace {
+    let mut grid = grid.clone();
+    if let Some((x, y)) = obstacle {
+        grid[x as usize][y as usize] = '#';
+    }
+    let mut guard = *guard;
+    let mut seen = HashMap::from([(guard, 0)]);
+    let mut states = vec![guard];
+    while guard.patrol(&grid) {
+        states.push(guard);
+        if let Some(&first) = seen.get(&guard) {
+            return PatrolTrace {
+                states,
+                repeated: Some(first),
+            };
+        }
+        seen.insert(guard, states.len() - 1);
+    }
+    PatrolTrace {
+        states,
+        repeated: None,
+    }
+}
+
+fn render_route(grid: &Grid, states: &[Guard], obstacle: Option<Coord>) -> String {
+    // (vertical, horizontal, turn) for every cell the guard walked through
+    let mut marks: HashMap<Coord, (bool, bool, bool)> = HashMap::new();
+    for pair in states.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let vertical = matches!(to.facing, Direction::Up | Direction::Down);
+        for coord in [from.coord, to.coord] {
+            let mark = marks.entry(coord).or_default();
+            if vertical {
+                mark.0 = true;
+            } else {
+                mark.1 = true;
+            }
+        }
+        if from.facing != to.facing {
+            marks.entry(from.coord).or_default().2 = true;
+        }
+    }
+
+    let mut canvas = grid.clone();
+    for ((x, y), (vertical, horizontal, turn)) in marks {
+        let cell = &mut canvas[x as usize][y as usize];
+        if *cell == '.' {
+            *cell = match (vertical, horizontal, turn) {
+                (true, true, _) | (_, _, true) => '+',
+                (true, false, _) => '|',
+                _ => '-',
+            };
+        }
+    }
+    if let Some((x, y)) = obstacle {
+        canvas[x as usize][y as usize] = 'O';
+    }
+    canvas
+        .iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn explain_obstacle(grid: &Grid, guard: &Guard, obstacle: Coord) -> Result<String> {
+    if grid_at(grid, obstacle) != Some('.') {
+        return err!("unable to place an obstacle at {obstacle:?}");
+    }
+    let trace = trace_patrol(grid, guard, Some(obstacle));
+    let mut out = format!("route with obstacle at {obstacle:?}:\n");
+    out.push_str(&render_route(grid, &trace.states, Some(obstacle)));
+    out.push('\n');
+    match trace.repeated {
+        Some(first) => {
+            let again = trace.states.len() - 1;
+            out.push_str(&format!(
+                "first repeated state: {:?} (step {first}, again at step {again})\n",
+                trace.states[first]
+            ));
+            out.push_str("loop:\n");
+            out.push_str(&render_route(grid, &trace.states[first..], Some(obstacle)));
+            out.push('\n');
+        }
+        None => out.push_str(&format!(
+            "no loop, guard leaves the map after {} steps\n",
+            trace.states.len() - 1
+        )),
+    }
+    Ok(out)
+}
+
+fn explain_from_args(grid: &Grid, guard: &Guard, args: &[String]) -> Result<()> {
+    let mut obstacle = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            return err!("missing value for {arg}");
+        };
+        match arg.as_str() {
+            "--explain" => match value.split_once(",") {
+                Some((x, y)) => obstacle = Some((x.trim().parse()?, y.trim().parse()?)),
+                None => return err!("obstacle should look like row,col: {value:?}"),
+            },
+            "--output" => output = Some(value),
+            _ => return err!("unknown argument: {arg}"),
+        }
+    }
+    let Some(obstacle) = obstacle else {
+        return Ok(());
+    };
+    let explanation = explain_obstacle(grid, guard, obstacle)?;
+    match output {
+        Some(path) => std::fs::write(path, explanation)?,
+        None => write!(io::stdout(), "{explanation}")?,
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
@@ -213,7 +460,10 @@ fn main() -> Result<()> {
     let (grid, guard) = parse_input(input)?;
     part1(&grid, &guard)?;
     part2(&grid, &guard)?;
+    part2_jump_table(&grid, &guard)?;
     // part2_bruteforce_trim(&grid, &guard)?;
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    explain_from_args(&grid, &guard, &args)?;
     Ok(())
 }
 
@@ -232,6 +482,11 @@ fn example_input() {
     let (grid, guard) = parse_input(input).unwrap();
     assert_eq!(part1(&grid, &guard).unwrap(), 41);
     assert_eq!(part2(&grid, &guard).unwrap(), 6);
+    assert_eq!(part2_jump_table(&grid, &guard).unwrap(), 6);
+    assert_eq!(
+        loop_obstacles(&grid, &guard),
+        vec![(6, 3), (7, 6), (7, 7), (8, 1), (8, 3), (9, 7)]
+    );
 }
 
 #[test]
@@ -247,6 +502,50 @@ fn test_guard() {
     assert_eq!(guard.coord, (2, 1));
 }
 
+#[test]
+fn explain_example_loop() {
+    let input = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+    let (grid, guard) = parse_input(input).unwrap();
+    let explanation = explain_obstacle(&grid, &guard, (6, 3)).unwrap();
+    assert_eq!(
+        explanation,
+        "route with obstacle at (6, 3):
+....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#...
+first repeated state: Guard { facing: Up, coord: (5, 4) } (step 1, again at step 19)
+loop:
+....#.....
+....+---+#
+....|...|.
+..#.|...|.
+....|..#|.
+....|...|.
+.#.O^---+.
+........#.
+#.........
+......#...
+"
+    );
+    assert!(explain_obstacle(&grid, &guard, (0, 4)).is_err());
+}
+
 #[test]
 fn real_input() {
     let input = std::fs::read_to_string("input/input.txt").unwrap();
@@ -254,4 +553,5 @@ fn real_input() {
     assert_eq!(part1(&grid, &guard).unwrap(), 5551);
     // assert_eq!(part2_bruteforce_trim(&grid, &guard).unwrap(), 1939);
     assert_eq!(part2(&grid, &guard).unwrap(), 1939);
+    assert_eq!(part2_jump_table(&grid, &guard).unwrap(), 1939);
 }
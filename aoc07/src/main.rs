@@ -33,7 +33,190 @@ impl FromStr for Equation {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Mul,
+    Concat,
+    Sub,
+    Xor,
+}
+
+const PART1_OPERATORS: [Operator; 2] = [Operator::Add, Operator::Mul];
+const PART2_OPERATORS: [Operator; 3] = [Operator::Add, Operator::Mul, Operator::Concat];
+
+impl FromStr for Operator {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "+" => Ok(Operator::Add),
+            "*" => Ok(Operator::Mul),
+            "||" => Ok(Operator::Concat),
+            "-" => Ok(Operator::Sub),
+            "^" => Ok(Operator::Xor),
+            _ => err!("unknown operator: {s:?}"),
+        }
+    }
+}
+
+impl Operator {
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Mul => "*",
+            Operator::Concat => "||",
+            Operator::Sub => "-",
+            Operator::Xor => "^",
+        }
+    }
+
+    fn apply(self, a: usize, b: usize) -> Option<usize> {
+        match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Mul => a.checked_mul(b),
+            Operator::Concat => a.checked_mul(digits_pow(b))?.checked_add(b),
+            Operator::Sub => a.checked_sub(b),
+            Operator::Xor => Some(a ^ b),
+        }
+    }
+
+    // the left operand `a` such that `a op b == target`
+    fn unapply(self, target: usize, b: usize) -> Option<usize> {
+        match self {
+            Operator::Add => target.checked_sub(b),
+            // a zero factor allows any left operand, `search` enumerates those forward
+            Operator::Mul => (b != 0 && target.is_multiple_of(b)).then(|| target / b),
+            Operator::Concat => {
+                let pow = digits_pow(b);
+                (target % pow == b).then(|| target / pow)
+            }
+            Operator::Sub => target.checked_add(b),
+            Operator::Xor => Some(target ^ b),
+        }
+    }
+}
+
+fn digits_pow(b: usize) -> usize {
+    10usize.pow(b.checked_ilog10().unwrap_or(0) + 1)
+}
+
 impl Equation {
+    // walks back from the target, peeling the last operand off with each operator's inverse;
+    // `visit` gets every operator sequence that works and returns true to stop the search
+    fn search(&self, operators: &[Operator], visit: &mut dyn FnMut(&[Operator]) -> bool) {
+        fn back(
+            target: usize,
+            operands: &[usize],
+            operators: &[Operator],
+            chosen: &mut Vec<Operator>,
+            visit: &mut dyn FnMut(&[Operator]) -> bool,
+        ) -> bool {
+            let (&last, rest) = operands.split_last().unwrap();
+            if rest.is_empty() {
+                if target != last {
+                    return false;
+                }
+                let sequence: Vec<_> = chosen.iter().rev().cloned().collect();
+                return visit(&sequence);
+            }
+            for &op in operators {
+                if op == Operator::Mul && last == 0 && target == 0 {
+                    // the prefix is absorbed by the zero, any sequence that evaluates will do
+                    chosen.push(op);
+                    let suffix: Vec<_> = chosen.iter().rev().cloned().collect();
+                    chosen.pop();
+                    let (&first, rest) = rest.split_first().unwrap();
+                    if forward(first, rest, operators, &mut vec![], &suffix, visit) {
+                        return true;
+                    }
+                } else if let Some(prev) = op.unapply(target, last) {
+                    chosen.push(op);
+                    let stop = back(prev, rest, operators, chosen, visit);
+                    chosen.pop();
+                    if stop {
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+
+        fn forward(
+            acc: usize,
+            operands: &[usize],
+            operators: &[Operator],
+            prefix: &mut Vec<Operator>,
+            suffix: &[Operator],
+            visit: &mut dyn FnMut(&[Operator]) -> bool,
+        ) -> bool {
+            let Some((&b, rest)) = operands.split_first() else {
+                return visit(&[prefix.as_slice(), suffix].concat());
+            };
+            for &op in operators {
+                if let Some(next) = op.apply(acc, b) {
+                    prefix.push(op);
+                    let stop = forward(next, rest, operators, prefix, suffix, visit);
+                    prefix.pop();
+                    if stop {
+                        return true;
+                    }
+                }
+            }
+            false
+        }
+
+        if !self.operands.is_empty() {
+            back(self.value, &self.operands, operators, &mut vec![], visit);
+        }
+    }
+
+    fn first_solution(&self, operators: &[Operator]) -> Option<Vec<Operator>> {
+        let mut found = None;
+        self.search(operators, &mut |sequence| {
+            found = Some(sequence.to_vec());
+            true
+        });
+        found
+    }
+
+    fn solutions(&self, operators: &[Operator]) -> Vec<Vec<Operator>> {
+        let mut found = vec![];
+        self.search(operators, &mut |sequence| {
+            found.push(sequence.to_vec());
+            false
+        });
+        found
+    }
+
+    fn count_solutions(&self, operators: &[Operator]) -> usize {
+        let mut count = 0;
+        self.search(operators, &mut |_| {
+            count += 1;
+            false
+        });
+        count
+    }
+
+    // operators are evaluated left to right, there is no precedence
+    fn evaluate(&self, sequence: &[Operator]) -> Option<usize> {
+        let (&first, rest) = self.operands.split_first()?;
+        if rest.len() != sequence.len() {
+            return None;
+        }
+        rest.iter()
+            .zip(sequence)
+            .try_fold(first, |acc, (&b, op)| op.apply(acc, b))
+    }
+
+    fn expression(&self, sequence: &[Operator]) -> String {
+        let mut s = self.operands[0].to_string();
+        for (b, op) in self.operands[1..].iter().zip(sequence) {
+            s += &format!(" {} {b}", op.symbol());
+        }
+        s
+    }
+
     fn test_operators_part1(&self) -> bool {
         fn dfs(current: usize, target: usize, operands: &[usize]) -> bool {
             (current == target && operands.is_empty()) || {
@@ -69,15 +252,32 @@ fn parse_input<T: AsRef<str>>(input: T) -> Result<Vec<Equation>> {
     input.as_ref().trim().lines().map(|l| l.parse()).collect()
 }
 
+fn calibration(equations: &[Equation], operators: &[Operator]) -> usize {
+    equations
+        .iter()
+        .filter(|e| e.first_solution(operators).is_some())
+        .map(|e| e.value)
+        .sum()
+}
+
 fn part1(equations: &[Equation]) -> Result<usize> {
     let _start = Instant::now();
 
+    let result = calibration(equations, &PART1_OPERATORS);
+    println!("part1: {result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+fn part1_forward(equations: &[Equation]) -> Result<usize> {
+    let _start = Instant::now();
+
     let result = equations
         .iter()
         .filter(|e| e.test_operators_part1())
         .map(|e| e.value)
         .sum();
-    println!("part1: {result}");
+    println!("part1 forward: {result}");
     writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
     Ok(result)
 }
@@ -85,22 +285,70 @@ fn part1(equations: &[Equation]) -> Result<usize> {
 fn part2(equations: &[Equation]) -> Result<usize> {
     let _start = Instant::now();
 
+    let result = calibration(equations, &PART2_OPERATORS);
+    println!("part2: {result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
+}
+
+fn part2_forward(equations: &[Equation]) -> Result<usize> {
+    let _start = Instant::now();
+
     let result = equations
         .iter()
         .filter(|e| e.test_operators_part2())
         .map(|e| e.value)
         .sum();
-    println!("part2: {result}");
+    println!("part2 forward: {result}");
     writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
     Ok(result)
 }
+
+// `--ops +,*,||,-,^` picks the operator set and prints how many expressions solve each
+// equation along with the first one, `--all` lists all of them
+fn show_expressions(equations: &[Equation], args: &[String]) -> Result<()> {
+    let Some(i) = args.iter().position(|a| a == "--ops") else {
+        return Ok(());
+    };
+    let Some(ops) = args.get(i + 1) else {
+        return err!("--ops needs a comma separated operator list");
+    };
+    let operators = ops
+        .split(',')
+        .map(|op| op.parse())
+        .collect::<Result<Vec<Operator>>>()?;
+    let all = args.iter().any(|a| a == "--all");
+    for equation in equations {
+        let count = equation.count_solutions(&operators);
+        if count == 0 {
+            continue;
+        }
+        println!("{}: {count} solution(s)", equation.value);
+        let shown = if all {
+            equation.solutions(&operators)
+        } else {
+            equation.first_solution(&operators).into_iter().collect()
+        };
+        for sequence in shown {
+            let Some(value) = equation.evaluate(&sequence) else {
+                return err!("{} overflows", equation.expression(&sequence));
+            };
+            println!("  {} = {value}", equation.expression(&sequence));
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
     let equations = parse_input(input)?;
     part1(&equations)?;
+    part1_forward(&equations)?;
     part2(&equations)?;
+    part2_forward(&equations)?;
+    show_expressions(&equations, &std::env::args().collect::<Vec<_>>())?;
     Ok(())
 }
 
@@ -117,7 +365,9 @@ fn example_input() -> Result<()> {
 292: 11 6 16 20";
     let equations = parse_input(input)?;
     assert_eq!(part1(&equations)?, 3749);
+    assert_eq!(part1_forward(&equations)?, 3749);
     assert_eq!(part2(&equations)?, 11387);
+    assert_eq!(part2_forward(&equations)?, 11387);
     Ok(())
 }
 
@@ -126,6 +376,62 @@ fn real_input() -> Result<()> {
     let input = std::fs::read_to_string("input/input.txt").unwrap();
     let equations = parse_input(input)?;
     assert_eq!(part1(&equations)?, 3119088655389);
+    assert_eq!(part1_forward(&equations)?, 3119088655389);
     assert_eq!(part2(&equations)?, 264184041398847);
+    assert_eq!(part2_forward(&equations)?, 264184041398847);
+    Ok(())
+}
+
+#[test]
+fn operator_expressions() -> Result<()> {
+    use Operator::*;
+
+    let equation: Equation = "3267: 81 40 27".parse()?;
+    let solutions = equation.solutions(&PART1_OPERATORS);
+    assert_eq!(solutions, vec![vec![Mul, Add], vec![Add, Mul]]);
+    assert_eq!(equation.expression(&solutions[1]), "81 + 40 * 27");
+    assert_eq!(equation.count_solutions(&PART1_OPERATORS), 2);
+
+    let equation: Equation = "7290: 6 8 6 15".parse()?;
+    let sequence = equation.first_solution(&PART2_OPERATORS).unwrap();
+    assert_eq!(equation.expression(&sequence), "6 * 8 || 6 * 15");
+    assert_eq!(equation.evaluate(&sequence), Some(7290));
+
+    let equation: Equation = "83: 17 5".parse()?;
+    assert_eq!(equation.first_solution(&PART2_OPERATORS), None);
+
+    let equation: Equation = "12: 17 5".parse()?;
+    assert_eq!(equation.solutions(&[Add, Sub, Xor]), vec![vec![Sub]]);
+    let equation: Equation = "20: 17 5".parse()?;
+    assert_eq!(equation.solutions(&[Sub, Xor]), vec![vec![Xor]]);
+
+    let operators = "+,*,||,-,^"
+        .split(',')
+        .map(|op| op.parse())
+        .collect::<Result<Vec<Operator>>>()?;
+    assert_eq!(operators, vec![Add, Mul, Concat, Sub, Xor]);
+    assert!("/".parse::<Operator>().is_err());
+
+    // a trailing zero factor makes the target reachable from any prefix
+    let equation: Equation = "0: 3 0".parse()?;
+    assert_eq!(equation.solutions(&PART1_OPERATORS), vec![vec![Mul]]);
+    let equation: Equation = "0: 2 3 0".parse()?;
+    assert_eq!(
+        equation.solutions(&PART1_OPERATORS),
+        vec![vec![Add, Mul], vec![Mul, Mul]]
+    );
+    for line in ["0: 3 0", "0: 2 3 0", "0: 5 0 7", "6: 2 0 3 6"] {
+        let equation: Equation = line.parse()?;
+        assert_eq!(
+            equation.first_solution(&PART1_OPERATORS).is_some(),
+            equation.test_operators_part1(),
+            "{line}"
+        );
+        assert_eq!(
+            equation.first_solution(&PART2_OPERATORS).is_some(),
+            equation.test_operators_part2(),
+            "{line}"
+        );
+    }
     Ok(())
 }
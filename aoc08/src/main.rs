@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::io::{self, Read, Write};
+use std::str::FromStr;
 use std::time::Instant;
 
 #[allow(unused_macros)]
@@ -22,13 +23,13 @@ fn parse_input<T: AsRef<str>>(input: T) -> Result<(Map, Coord)> {
         .collect();
     let bound = (input.len(), input[0].len());
     let mut map = Map::new();
-    (0..bound.0).for_each(|i| {
-        for j in 0..bound.1 {
-            if input[i][j] != '.' {
-                map.insert((i as i32, j as i32), input[i][j]);
+    for (i, row) in input.iter().enumerate() {
+        for (j, &f) in row.iter().enumerate() {
+            if f != '.' {
+                map.insert((i as i32, j as i32), f);
             }
         }
-    });
+    }
     Ok((map, (bound.0 as i32, bound.1 as i32)))
 }
 
@@ -45,75 +46,222 @@ fn in_bound(c: Coord, bound: Coord) -> bool {
     c.0 >= 0 && c.1 >= 0 && c.0 < bound.0 && c.1 < bound.1
 }
 
-fn find_antinodes(a: Coord, b: Coord) -> [Coord; 2] {
-    let dx = b.0 - a.0;
-    let dy = b.1 - a.1;
-    let (x, y) = (a.0, a.1);
-    // (x + dx, y + dy) => b
-    [(x - dx, y - dy), (x + 2 * dx, y + 2 * dy)]
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
 }
 
-fn part1(map: &Map, bound: Coord) -> Result<usize> {
-    let _start = Instant::now();
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Resonance {
+    // points p on the line through a and b where one antenna is `ratio` times farther
+    // from p than the other, `inner` also keeps the points between the two antennas
+    Harmonics { ratios: Vec<i32>, inner: bool },
+    // every grid point on the line, `reduce` steps by (dx, dy) / gcd instead of (dx, dy)
+    Line { reduce: bool },
+    // the frequency makes no antinodes
+    Silent,
+}
+
+impl FromStr for Resonance {
+    type Err = Box<dyn Error>;
 
-    let freqs = map_to_freq_coords(map);
-    let mut antinodes: HashSet<Coord> = HashSet::new();
-
-    for coords in freqs.values() {
-        for i in 0..coords.len() {
-            for j in i + 1..coords.len() {
-                let a = coords[i];
-                let b = coords[j];
-                for c in find_antinodes(a, b) {
-                    if in_bound(c, bound) {
-                        antinodes.insert(c);
+    // "line", "line-gcd", "silent" or harmonic ratios such as "h2,3" and "h2+inner"
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "line" => Ok(Resonance::Line { reduce: false }),
+            "line-gcd" => Ok(Resonance::Line { reduce: true }),
+            "silent" => Ok(Resonance::Silent),
+            _ => {
+                let Some(ratios) = s.strip_prefix('h') else {
+                    return err!("unknown resonance: {s:?}");
+                };
+                let (ratios, inner) = match ratios.strip_suffix("+inner") {
+                    Some(ratios) => (ratios, true),
+                    None => (ratios, false),
+                };
+                let ratios: Vec<i32> = ratios
+                    .split(',')
+                    .map(|n| n.parse())
+                    .collect::<std::result::Result<_, _>>()?;
+                if let Some(n) = ratios.iter().find(|&&n| n <= 0) {
+                    return err!("harmonic ratios must be positive: {n}");
+                }
+                Ok(Resonance::Harmonics { ratios, inner })
+            }
+        }
+    }
+}
+
+impl Resonance {
+    fn antinodes(&self, a: Coord, b: Coord, bound: Coord) -> Vec<Coord> {
+        match self {
+            Resonance::Harmonics { ratios, inner } => {
+                // p = (n * far + sign * near) / (n + sign), kept when it lands on a grid point
+                let at = |n: i32, sign: i32, near: Coord, far: Coord| {
+                    let d = n + sign;
+                    let (x, y) = (n * far.0 + sign * near.0, n * far.1 + sign * near.1);
+                    (d != 0 && x % d == 0 && y % d == 0).then_some((x / d, y / d))
+                };
+                let mut points = vec![];
+                for &n in ratios {
+                    for (near, far) in [(a, b), (b, a)] {
+                        points.extend(at(n, -1, near, far));
+                        if *inner {
+                            points.extend(at(n, 1, near, far));
+                        }
                     }
                 }
+                points.retain(|&c| in_bound(c, bound));
+                points
             }
+            Resonance::Line { reduce } => {
+                let (mut dx, mut dy) = (b.0 - a.0, b.1 - a.1);
+                if *reduce {
+                    let g = gcd(dx, dy);
+                    (dx, dy) = (dx / g, dy / g);
+                }
+                let (x, y) = a;
+                (0..)
+                    .map(|i| (x - i * dx, y - i * dy))
+                    .take_while(|&c| in_bound(c, bound))
+                    .chain(
+                        (1..)
+                            .map(|i| (x + i * dx, y + i * dy))
+                            .take_while(|&c| in_bound(c, bound)),
+                    )
+                    .collect()
+            }
+            Resonance::Silent => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ResonanceModel {
+    default: Resonance,
+    rules: HashMap<Freq, Resonance>,
+}
+
+impl ResonanceModel {
+    fn new(default: Resonance) -> Self {
+        ResonanceModel {
+            default,
+            rules: HashMap::new(),
+        }
+    }
+
+    fn with_rule(mut self, freq: Freq, resonance: Resonance) -> Self {
+        self.rules.insert(freq, resonance);
+        self
+    }
+
+    fn rule(&self, freq: Freq) -> &Resonance {
+        self.rules.get(&freq).unwrap_or(&self.default)
+    }
+
+    fn antinodes(&self, map: &Map, bound: Coord) -> HashMap<Freq, HashSet<Coord>> {
+        map_to_freq_coords(map)
+            .into_iter()
+            .map(|(freq, coords)| {
+                let resonance = self.rule(freq);
+                let mut antinodes = HashSet::new();
+                for (i, &a) in coords.iter().enumerate() {
+                    for &b in &coords[i + 1..] {
+                        antinodes.extend(resonance.antinodes(a, b, bound));
+                    }
+                }
+                (freq, antinodes)
+            })
+            .collect()
+    }
+}
+
+fn all_antinodes(groups: &HashMap<Freq, HashSet<Coord>>) -> HashSet<Coord> {
+    groups.values().flatten().cloned().collect()
+}
+
+// antennas keep their frequency, antinodes on empty cells are drawn as '#'
+fn render(map: &Map, bound: Coord, antinodes: &HashSet<Coord>) -> String {
+    let mut s = String::new();
+    for x in 0..bound.0 {
+        for y in 0..bound.1 {
+            s.push(match map.get(&(x, y)) {
+                Some(&f) => f,
+                None if antinodes.contains(&(x, y)) => '#',
+                None => '.',
+            });
         }
+        s.push('\n');
     }
+    s
+}
 
-    let result = antinodes.len();
+fn part1(map: &Map, bound: Coord) -> Result<usize> {
+    let _start = Instant::now();
+
+    let model = ResonanceModel::new(Resonance::Harmonics {
+        ratios: vec![2],
+        inner: false,
+    });
+    let result = all_antinodes(&model.antinodes(map, bound)).len();
 
     println!("part1: {result}");
     writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
     Ok(result)
 }
 
-fn find_antinodes_part2(a: Coord, b: Coord, bound: Coord) -> Vec<Coord> {
-    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
-    let (x, y) = a;
+fn part2(map: &Map, bound: Coord) -> Result<usize> {
+    let _start = Instant::now();
+
+    let model = ResonanceModel::new(Resonance::Line { reduce: false });
+    let result = all_antinodes(&model.antinodes(map, bound)).len();
 
-    (0..)
-        .map(|i| (x - i * dx, y - i * dy))
-        .take_while(|&c| in_bound(c, bound))
-        .chain(
-            (0..)
-                .map(|i| (x + i * dx, y + i * dy))
-                .take_while(|&c| in_bound(c, bound)),
-        )
-        .collect()
+    println!("part2: {result}");
+    writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
+    Ok(result)
 }
 
-fn part2(map: &Map, bound: Coord) -> Result<usize> {
+// `--model line-gcd` sets the default resonance, `--rule A=silent` overrides one frequency
+// and `--render` draws the antinodes over the map
+fn custom_model(map: &Map, bound: Coord, args: &[String]) -> Result<usize> {
     let _start = Instant::now();
 
-    let freqs = map_to_freq_coords(map);
-    let mut antinodes: HashSet<Coord> = HashSet::new();
-
-    for coords in freqs.values() {
-        for i in 0..coords.len() {
-            for j in i + 1..coords.len() {
-                let a = coords[i];
-                let b = coords[j];
-                antinodes.extend(find_antinodes_part2(a, b, bound).iter());
+    let mut model = ResonanceModel::new(Resonance::Line { reduce: true });
+    let mut render_map = false;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || match args.next() {
+            Some(value) => Ok(value),
+            None => err!("{flag} needs a value"),
+        };
+        match flag.as_str() {
+            "--model" => model.default = value()?.parse()?,
+            "--render" => render_map = true,
+            "--rule" => {
+                let value = value()?;
+                let Some((freq, resonance)) = value.split_once('=') else {
+                    return err!("--rule expects <freq>=<resonance>: {value:?}");
+                };
+                let mut chars = freq.chars();
+                let (Some(freq), None) = (chars.next(), chars.next()) else {
+                    return err!("frequency must be a single character: {freq:?}");
+                };
+                model = model.with_rule(freq, resonance.parse()?);
             }
+            _ => return err!("unknown flag: {flag:?}"),
         }
     }
+    let groups = model.antinodes(map, bound);
+    let result = all_antinodes(&groups).len();
 
-    let result = antinodes.len();
-
-    println!("part2: {result}");
+    println!("custom model: {result}");
+    let mut freqs: Vec<_> = groups.keys().collect();
+    freqs.sort();
+    for f in freqs {
+        println!("  {f}: {}", groups[f].len());
+    }
+    if render_map {
+        print!("{}", render(map, bound, &all_antinodes(&groups)));
+    }
     writeln!(io::stdout(), "> Time elapsed is: {:?}", _start.elapsed())?;
     Ok(result)
 }
@@ -125,6 +273,7 @@ fn main() -> Result<()> {
     let (map, bound) = parse_input(input)?;
     part1(&map, bound)?;
     part2(&map, bound)?;
+    custom_model(&map, bound, &std::env::args().skip(1).collect::<Vec<_>>())?;
     Ok(())
 }
 
@@ -145,6 +294,18 @@ fn example_input() -> Result<()> {
     let (map, bound) = parse_input(input)?;
     assert_eq!(part1(&map, bound)?, 14);
     assert_eq!(part2(&map, bound)?, 34);
+    assert_eq!(custom_model(&map, bound, &[])?, 34);
+    let args = ["--model", "h2", "--rule", "A=line"].map(String::from);
+    assert_eq!(custom_model(&map, bound, &args)?, 24);
+    for bad in [
+        &["--modle", "h2"][..],
+        &["--model"],
+        &["--model", "h0"],
+        &["--model", "h2,-1"],
+    ] {
+        let args: Vec<_> = bad.iter().map(|a| a.to_string()).collect();
+        assert!(custom_model(&map, bound, &args).is_err(), "{bad:?}");
+    }
     Ok(())
 }
 
@@ -156,3 +317,50 @@ fn real_input() -> Result<()> {
     assert_eq!(part2(&map, bound)?, 1293);
     Ok(())
 }
+
+#[test]
+fn resonance_models() -> Result<()> {
+    let input = "..........
+...#......
+#.........
+....a.....
+........a.
+.....a....
+..#.......
+......A...
+..........
+..........";
+    let (map, bound) = parse_input(input.replace('#', "."))?;
+    let part1 = ResonanceModel::new(Resonance::Harmonics {
+        ratios: vec![2],
+        inner: false,
+    });
+    let groups = part1.antinodes(&map, bound);
+    assert_eq!(groups[&'A'], HashSet::new());
+    assert_eq!(
+        render(&map, bound, &all_antinodes(&groups)),
+        input.to_owned() + "\n"
+    );
+
+    // a step of (2, 4) skips the grid points in between unless it is reduced
+    let (map, bound) = parse_input("a....\n.....\n..a..\n.....\n.....")?;
+    let line = |reduce| ResonanceModel::new(Resonance::Line { reduce });
+    assert_eq!(all_antinodes(&line(false).antinodes(&map, bound)).len(), 3);
+    assert_eq!(all_antinodes(&line(true).antinodes(&map, bound)).len(), 5);
+
+    let (map, bound) = parse_input("a.......a\n.........")?;
+    let harmonics =
+        |ratios: Vec<i32>, inner| ResonanceModel::new(Resonance::Harmonics { ratios, inner });
+    let groups = harmonics(vec![2, 3], true).antinodes(&map, bound);
+    let mut antinodes: Vec<_> = groups[&'a'].iter().cloned().collect();
+    antinodes.sort();
+    // 3:1 lands on 2 and 6, 2:1 falls between grid points and the outer ones are off the map
+    assert_eq!(antinodes, vec![(0, 2), (0, 6)]);
+
+    let (map, bound) = parse_input("a..b....\n.a..b...\n........")?;
+    let model = harmonics(vec![2], false).with_rule('b', Resonance::Silent);
+    let groups = model.antinodes(&map, bound);
+    assert_eq!(groups[&'a'].len(), 1);
+    assert!(groups[&'b'].is_empty());
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use std::collections::{HashSet, VecDeque};
 use std::error::Error;
 use std::io::{self, Read};
+use std::ops::RangeInclusive;
 use std::time::Instant;
 
 #[allow(unused_macros)]
@@ -11,26 +12,291 @@ macro_rules! err {
 type Result<T> = ::std::result::Result<T, Box<dyn Error>>;
 
 type TopoMap = Vec<Vec<u8>>;
+type Coord = (usize, usize);
+
+// cells that no trail can enter, written as '.'
+const IMPASSABLE: u8 = u8::MAX;
 
 fn parse_input<T: AsRef<str>>(input: T) -> Result<TopoMap> {
-    Ok(input
+    input
         .as_ref()
         .trim()
         .lines()
-        .map(|l| l.trim().bytes().map(|b| b - b'0').collect())
-        .collect())
+        .map(|l| {
+            l.trim()
+                .bytes()
+                .map(|b| match b {
+                    b'0'..=b'9' => Ok(b - b'0'),
+                    b'.' => Ok(IMPASSABLE),
+                    _ => err!("unexpected cell {:?} in {l:?}", b as char),
+                })
+                .collect()
+        })
+        .collect()
 }
 
 fn find_trailheads(map: &TopoMap) -> Vec<(usize, usize)> {
-    let mut trailheads = vec![];
+    find_cells(map, 0)
+}
+
+fn find_cells(map: &TopoMap, height: u8) -> Vec<Coord> {
+    let mut cells = vec![];
     for (i, row) in map.iter().enumerate() {
-        for (j, height) in row.iter().enumerate() {
-            if height == &0 {
-                trailheads.push((i, j));
+        for (j, &h) in row.iter().enumerate() {
+            if h == height {
+                cells.push((i, j));
+            }
+        }
+    }
+    cells
+}
+
+#[derive(Debug, Clone)]
+struct TerrainRules {
+    // allowed height change for a single move, negative steps go down
+    steps: RangeInclusive<i8>,
+    start: u8,
+    end: u8,
+    diagonal: bool,
+}
+
+impl Default for TerrainRules {
+    fn default() -> Self {
+        TerrainRules {
+            steps: 1..=1,
+            start: 0,
+            end: 9,
+            diagonal: false,
+        }
+    }
+}
+
+impl TerrainRules {
+    fn moves(&self) -> &'static [(isize, isize)] {
+        const ORTHOGONAL: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        const ALL: [(isize, isize); 8] = [
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (-1, 1),
+            (1, -1),
+            (1, 1),
+        ];
+        if self.diagonal { &ALL } else { &ORTHOGONAL }
+    }
+
+    fn next(&self, map: &TopoMap, (x, y): Coord) -> Vec<Coord> {
+        let height = map[x][y] as i16;
+        self.moves()
+            .iter()
+            .filter_map(|&(dx, dy)| {
+                let (x, y) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+                let h = *map.get(x)?.get(y)?;
+                let step = h as i16 - height;
+                (h != IMPASSABLE && self.steps.contains(&(step as i8))).then_some((x, y))
+            })
+            .collect()
+    }
+
+    // every step moves the same way and never stays level, so trails can't loop
+    fn is_monotone(&self) -> bool {
+        *self.steps.start() > 0 || *self.steps.end() < 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TrailStats {
+    trailhead: Coord,
+    // distinct trail ends reachable from the trailhead
+    score: usize,
+    // distinct trails from the trailhead
+    rating: usize,
+}
+
+fn trail_stats(map: &TopoMap, rules: &TerrainRules) -> Vec<TrailStats> {
+    if rules.is_monotone() {
+        trail_stats_dp(map, rules)
+    } else {
+        trail_stats_enumerated(map, rules)
+    }
+}
+
+// settles cells from the far end of the climb back to the start, so every cell only
+// depends on cells already done: ratings add up over the next cells and scores
+// union bitsets of the reachable trail ends
+fn trail_stats_dp(map: &TopoMap, rules: &TerrainRules) -> Vec<TrailStats> {
+    let mut cells: Vec<_> = (0..map.len())
+        .flat_map(|i| (0..map[i].len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| map[i][j] != IMPASSABLE)
+        .collect();
+    cells.sort_by_key(|&(i, j)| map[i][j]);
+    if *rules.steps.start() > 0 {
+        cells.reverse();
+    }
+    let index = |(x, y): Coord| x * map[0].len() + y;
+    let ends = find_cells(map, rules.end);
+    let words = ends.len().div_ceil(64);
+    let size = map.len() * map[0].len();
+    let mut rating = vec![0; size];
+    let mut reach = vec![0u64; size * words];
+    for (bit, &c) in ends.iter().enumerate() {
+        rating[index(c)] = 1;
+        reach[index(c) * words + bit / 64] |= 1 << (bit % 64);
+    }
+    for c in cells {
+        if map[c.0][c.1] == rules.end {
+            continue;
+        }
+        let i = index(c);
+        for n in rules.next(map, c) {
+            let k = index(n);
+            rating[i] += rating[k];
+            for w in 0..words {
+                reach[i * words + w] |= reach[k * words + w];
+            }
+        }
+    }
+    find_cells(map, rules.start)
+        .into_iter()
+        .map(|trailhead| {
+            let i = index(trailhead);
+            TrailStats {
+                trailhead,
+                score: reach[i * words..(i + 1) * words]
+                    .iter()
+                    .map(|w| w.count_ones() as usize)
+                    .sum(),
+                rating: rating[i],
+            }
+        })
+        .collect()
+}
+
+// level or back and forth steps can loop, so only trails that never revisit a cell
+// count and they have to be walked one by one
+fn trail_stats_enumerated(map: &TopoMap, rules: &TerrainRules) -> Vec<TrailStats> {
+    find_cells(map, rules.start)
+        .into_iter()
+        .map(|trailhead| {
+            let mut ends = HashSet::new();
+            let mut rating = 0;
+            for trail in Trails::new(map, rules.clone()).from(trailhead) {
+                ends.insert(*trail.last().unwrap());
+                rating += 1;
+            }
+            TrailStats {
+                trailhead,
+                score: ends.len(),
+                rating,
+            }
+        })
+        .collect()
+}
+
+fn part1(map: &TopoMap) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = trail_stats(map, &TerrainRules::default())
+        .iter()
+        .map(|s| s.score)
+        .sum();
+    println!("part1: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+fn part2(map: &TopoMap) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = trail_stats(map, &TerrainRules::default())
+        .iter()
+        .map(|s| s.rating)
+        .sum();
+    println!("part2: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+// lazily yields every trail as the cells from its start to its end, a trail stops at the
+// first end cell and never visits a cell twice, so a start at the end height is a trail
+// of its own as in trail_stats_dp
+struct Trails<'a> {
+    map: &'a TopoMap,
+    rules: TerrainRules,
+    starts: Vec<Coord>,
+    end: Option<Coord>,
+    // each cell on the current path with the next cells still to try from it
+    stack: Vec<(Coord, Vec<Coord>)>,
+}
+
+impl<'a> Trails<'a> {
+    fn new(map: &'a TopoMap, rules: TerrainRules) -> Self {
+        let mut starts = find_cells(map, rules.start);
+        starts.reverse();
+        Trails {
+            map,
+            rules,
+            starts,
+            end: None,
+            stack: vec![],
+        }
+    }
+
+    fn from(mut self, start: Coord) -> Self {
+        self.starts.retain(|&c| c == start);
+        self
+    }
+
+    fn to(mut self, end: Coord) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    fn pending(&self, c: Coord) -> Vec<Coord> {
+        let mut next = self.rules.next(self.map, c);
+        next.reverse();
+        next
+    }
+}
+
+impl Iterator for Trails<'_> {
+    type Item = Vec<Coord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((_, pending)) = self.stack.last_mut() else {
+                let start = self.starts.pop()?;
+                if self.map[start.0][start.1] == self.rules.end {
+                    if self.end.is_none_or(|end| end == start) {
+                        return Some(vec![start]);
+                    }
+                    continue;
+                }
+                let pending = self.pending(start);
+                self.stack.push((start, pending));
+                continue;
+            };
+            let Some(c) = pending.pop() else {
+                self.stack.pop();
+                continue;
+            };
+            if self.stack.iter().any(|&(p, _)| p == c) {
+                continue;
+            }
+            if self.map[c.0][c.1] == self.rules.end {
+                if self.end.is_none_or(|end| end == c) {
+                    let mut trail: Vec<_> = self.stack.iter().map(|&(c, _)| c).collect();
+                    trail.push(c);
+                    return Some(trail);
+                }
+            } else {
+                let pending = self.pending(c);
+                self.stack.push((c, pending));
             }
         }
     }
-    trailheads
 }
 
 fn dfs_find_hiking_trail(coord: (usize, usize), map: &TopoMap) -> Vec<(usize, usize)> {
@@ -56,7 +322,7 @@ fn dfs_find_hiking_trail(coord: (usize, usize), map: &TopoMap) -> Vec<(usize, us
     }
 }
 
-fn part1(map: &TopoMap) -> Result<usize> {
+fn part1_dfs(map: &TopoMap) -> Result<usize> {
     let _start = Instant::now();
 
     let trailheads = find_trailheads(map);
@@ -69,12 +335,12 @@ fn part1(map: &TopoMap) -> Result<usize> {
                 .len()
         })
         .sum();
-    println!("part1: {result}");
+    println!("part1 with dfs: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
 
-fn part2(map: &TopoMap) -> Result<usize> {
+fn part2_dfs(map: &TopoMap) -> Result<usize> {
     let _start = Instant::now();
 
     let trailheads = find_trailheads(map);
@@ -83,7 +349,7 @@ fn part2(map: &TopoMap) -> Result<usize> {
         .map(|&c| dfs_find_hiking_trail(c, map).len())
         .sum::<usize>();
 
-    println!("part2: {result}");
+    println!("part2 with dfs: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
@@ -109,7 +375,7 @@ fn bfs_find_hiking_trail(coord: (usize, usize), map: &TopoMap, part2: bool) -> u
                 if y > 0 && map[x][y - 1] == height + 1 {
                     queue.push_back((x, y - 1));
                 }
-                if y + 1 < map.len() && map[x][y + 1] == height + 1 {
+                if y + 1 < map[x].len() && map[x][y + 1] == height + 1 {
                     queue.push_back((x, y + 1));
                 }
             }
@@ -144,6 +410,84 @@ fn part2_bfs(map: &TopoMap) -> Result<usize> {
     Ok(result)
 }
 
+fn parse_coord(s: &str) -> Result<Coord> {
+    match s.split_once(',') {
+        Some((x, y)) => Ok((x.trim().parse()?, y.trim().parse()?)),
+        None => err!("expected <row>,<col>: {s:?}"),
+    }
+}
+
+// `--steps <min>..<max>`, `--start <height>`, `--end <height>` and `--diagonal` change the
+// terrain rules, `--trails` lists every trail narrowed down by `--from` and `--to <row>,<col>`
+#[derive(Debug, Clone, Default)]
+struct CustomArgs {
+    rules: TerrainRules,
+    trails: bool,
+    from: Option<Coord>,
+    to: Option<Coord>,
+}
+
+fn parse_height(flag: &str, value: &str) -> Result<u8> {
+    match value.parse() {
+        Ok(h) if h <= 9 => Ok(h),
+        _ => err!("{flag} expects a height from 0 to 9: {value:?}"),
+    }
+}
+
+fn args_from(args: &[String]) -> Result<CustomArgs> {
+    let mut custom = CustomArgs::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || match args.next() {
+            Some(value) => Ok(value),
+            None => err!("{flag} needs a value"),
+        };
+        match flag.as_str() {
+            "--steps" => {
+                let value = value()?;
+                let Some((min, max)) = value.split_once("..") else {
+                    return err!("--steps expects <min>..<max>: {value:?}");
+                };
+                custom.rules.steps = min.parse()?..=max.parse()?;
+            }
+            "--start" => custom.rules.start = parse_height(flag, value()?)?,
+            "--end" => custom.rules.end = parse_height(flag, value()?)?,
+            "--diagonal" => custom.rules.diagonal = true,
+            "--trails" => custom.trails = true,
+            "--from" => custom.from = Some(parse_coord(value()?)?),
+            "--to" => custom.to = Some(parse_coord(value()?)?),
+            _ => return err!("unknown flag: {flag:?}"),
+        }
+    }
+    Ok(custom)
+}
+
+fn custom_rules(map: &TopoMap, args: &[String]) -> Result<(usize, usize)> {
+    let _start = Instant::now();
+
+    let custom = args_from(args)?;
+    let rules = custom.rules;
+    let stats = trail_stats(map, &rules);
+    let score = stats.iter().map(|s| s.score).sum();
+    let rating = stats.iter().map(|s| s.rating).sum();
+    println!("custom rules {rules:?}: score {score}, rating {rating}");
+    if custom.trails {
+        let mut trails = Trails::new(map, rules);
+        if let Some(from) = custom.from {
+            trails = trails.from(from);
+        }
+        if let Some(to) = custom.to {
+            trails = trails.to(to);
+        }
+        for trail in trails {
+            let cells: Vec<_> = trail.iter().map(|(x, y)| format!("{x},{y}")).collect();
+            println!("{}", cells.join(" -> "));
+        }
+    }
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok((score, rating))
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
@@ -151,8 +495,14 @@ fn main() -> Result<()> {
     let map = parse_input(input)?;
     part1(&map)?;
     part2(&map)?;
+    part1_dfs(&map)?;
+    part2_dfs(&map)?;
     part1_bfs(&map)?;
     part2_bfs(&map)?;
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        custom_rules(&map, &args)?;
+    }
     Ok(())
 }
 
@@ -169,8 +519,10 @@ fn example_input() -> Result<()> {
 ";
     let map = parse_input(input)?;
     assert_eq!(part1(&map)?, 36);
+    assert_eq!(part1_dfs(&map)?, 36);
     assert_eq!(part1_bfs(&map)?, 36);
     assert_eq!(part2(&map)?, 81);
+    assert_eq!(part2_dfs(&map)?, 81);
     assert_eq!(part2_bfs(&map)?, 81);
     Ok(())
 }
@@ -180,9 +532,146 @@ fn real_input() -> Result<()> {
     let input = std::fs::read_to_string("input/input.txt").unwrap();
     let map = parse_input(input)?;
     assert_eq!(part1(&map)?, 566);
+    assert_eq!(part1_dfs(&map)?, 566);
     assert_eq!(part1_bfs(&map)?, 566);
     assert_eq!(part2(&map)?, 1324);
+    assert_eq!(part2_dfs(&map)?, 1324);
     assert_eq!(part2_bfs(&map)?, 1324);
     assert_eq!(2, 2);
     Ok(())
 }
+
+#[test]
+fn trail_enumeration() -> Result<()> {
+    let input = "89010123
+78121874
+87430965
+96549874
+45678903
+32019012
+01329801
+10456732
+";
+    let map = parse_input(input)?;
+    let stats = trail_stats(&map, &TerrainRules::default());
+    assert_eq!(
+        stats.iter().map(|s| s.score).collect::<Vec<_>>(),
+        vec![5, 6, 5, 3, 1, 3, 5, 3, 5]
+    );
+    assert_eq!(
+        stats.iter().map(|s| s.rating).collect::<Vec<_>>(),
+        vec![20, 24, 10, 4, 1, 4, 5, 8, 5]
+    );
+    assert_eq!(Trails::new(&map, TerrainRules::default()).count(), 81);
+    assert_eq!(
+        Trails::new(&map, TerrainRules::default())
+            .from((0, 2))
+            .count(),
+        20
+    );
+    let ends: HashSet<_> = Trails::new(&map, TerrainRules::default())
+        .from((0, 2))
+        .map(|t| t[9])
+        .collect();
+    assert_eq!(ends.len(), 5);
+    assert_eq!(
+        Trails::new(&map, TerrainRules::default())
+            .to((3, 4))
+            .count(),
+        17
+    );
+
+    // wider than it is tall
+    let map = parse_input("0123456789\n1234567898")?;
+    assert_eq!(part1(&map)?, 2);
+    assert_eq!(part1_bfs(&map)?, 2);
+    assert_eq!(part2(&map)?, 10);
+    assert_eq!(part2_bfs(&map)?, 10);
+    let trails: Vec<_> = Trails::new(&map, TerrainRules::default())
+        .to((0, 9))
+        .collect();
+    assert_eq!(trails, vec![(0..10).map(|y| (0, y)).collect::<Vec<_>>()]);
+    Ok(())
+}
+
+#[test]
+fn terrain_rules() -> Result<()> {
+    let map = parse_input(
+        "..90..9
+...1.98
+...2..7
+6543456
+765.987
+876....
+987....",
+    )?;
+    assert_eq!(part1(&map)?, 4);
+    assert_eq!(part1_bfs(&map)?, 4);
+    let map = parse_input(
+        ".....0.
+..4321.
+..5..2.
+..6543.
+..7..4.
+..8765.
+..9....",
+    )?;
+    assert_eq!(part2(&map)?, 3);
+    assert_eq!(part2_dfs(&map)?, 3);
+    assert!(parse_input("01\n2x").is_err());
+
+    // descending from every 9 finds the same pairs and trails
+    let map = parse_input(
+        "89010123
+78121874
+87430965
+96549874
+45678903
+32019012
+01329801
+10456732",
+    )?;
+    let args = ["--steps", "-1..-1", "--start", "9", "--end", "0"].map(String::from);
+    assert_eq!(custom_rules(&map, &args)?, (36, 81));
+
+    let map = parse_input("0.\n.1")?;
+    let args = ["--end", "1"].map(String::from);
+    assert_eq!(custom_rules(&map, &args)?, (0, 0));
+    let args = ["--end", "1", "--diagonal"].map(String::from);
+    assert_eq!(custom_rules(&map, &args)?, (1, 1));
+
+    // level steps can loop around the 1s, only simple trails count
+    let map = parse_input("011\n112")?;
+    let args = ["--steps", "0..1", "--end", "2"].map(String::from);
+    assert_eq!(custom_rules(&map, &args)?, (1, 4));
+    let args = ["--steps", "0..2", "--end", "2", "--diagonal"].map(String::from);
+    let rules = args_from(&args)?.rules;
+    assert!(!rules.is_monotone());
+    assert_eq!(
+        Trails::new(&map, rules.clone()).count(),
+        trail_stats_enumerated(&map, &rules)[0].rating
+    );
+
+    // a start at the end height is a trail by itself, whichever way it is counted
+    let args = ["--start", "1", "--end", "1"].map(String::from);
+    let rules = args_from(&args)?.rules;
+    assert_eq!(custom_rules(&map, &args)?, (4, 4));
+    assert_eq!(
+        trail_stats_enumerated(&map, &rules),
+        trail_stats_dp(&map, &rules)
+    );
+    let args = ["--steps", "0..1", "--start", "1", "--end", "1"].map(String::from);
+    assert_eq!(custom_rules(&map, &args)?, (4, 4));
+    assert_eq!(Trails::new(&map, args_from(&args)?.rules).count(), 4);
+
+    for bad in [
+        &["--step", "1..2"][..],
+        &["--end", "255"],
+        &["--start", "10"],
+        &["--to"],
+    ] {
+        let args: Vec<_> = bad.iter().map(|a| a.to_string()).collect();
+        assert!(custom_rules(&map, &args).is_err(), "{bad:?}");
+    }
+    Ok(())
+}
@@ -19,9 +19,159 @@ fn parse_input<T: AsRef<str>>(input: T) -> Result<Vec<Vec<char>>> {
         .collect())
 }
 
+struct DisjointSet {
+    parent: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(n: usize) -> Self {
+        DisjointSet {
+            parent: (0..n).collect(),
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[a.max(b)] = a.min(b);
+        }
+    }
+}
+
+// one region label per cell, numbered in reading order of each region's first cell
+struct Labels {
+    width: usize,
+    height: usize,
+    grid: Vec<usize>,
+    count: usize,
+}
+
+impl Labels {
+    fn get(&self, x: isize, y: isize) -> Option<usize> {
+        (x >= 0 && y >= 0 && (x as usize) < self.height && (y as usize) < self.width)
+            .then(|| self.grid[x as usize * self.width + y as usize])
+    }
+}
+
+// scanline pass joining each cell with the same plant above and to the left,
+// then a second pass flattening the union find into dense labels
+fn label_regions(map: &[Vec<char>]) -> Labels {
+    let (height, width) = (map.len(), map[0].len());
+    let mut set = DisjointSet::new(height * width);
+    for i in 0..height {
+        for j in 0..width {
+            if i > 0 && map[i - 1][j] == map[i][j] {
+                set.union(i * width + j, (i - 1) * width + j);
+            }
+            if j > 0 && map[i][j - 1] == map[i][j] {
+                set.union(i * width + j, i * width + j - 1);
+            }
+        }
+    }
+    let mut dense = vec![usize::MAX; height * width];
+    let mut count = 0;
+    let mut grid = vec![0; height * width];
+    for (k, label) in grid.iter_mut().enumerate() {
+        let root = set.find(k);
+        if dense[root] == usize::MAX {
+            dense[root] = count;
+            count += 1;
+        }
+        *label = dense[root];
+    }
+    Labels {
+        width,
+        height,
+        grid,
+        count,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RegionStats {
+    plant: char,
+    area: usize,
+    perimeter: usize,
+    // a polygon has as many sides as corners
+    sides: usize,
+}
+
+// a single sweep over the label grid: every cell adds its fences and, for each of its
+// four diagonal quadrants, a convex or concave corner
+fn region_stats(map: &[Vec<char>], labels: &Labels) -> Vec<RegionStats> {
+    let mut stats = vec![
+        RegionStats {
+            plant: ' ',
+            area: 0,
+            perimeter: 0,
+            sides: 0,
+        };
+        labels.count
+    ];
+    for x in 0..labels.height as isize {
+        for y in 0..labels.width as isize {
+            let label = labels.get(x, y);
+            let same = |dx, dy| labels.get(x + dx, y + dy) == label;
+            let region = &mut stats[label.unwrap()];
+            region.plant = map[x as usize][y as usize];
+            region.area += 1;
+            region.perimeter += [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .filter(|&(dx, dy)| !same(dx, dy))
+                .count();
+            for (dx, dy) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+                let (row, col) = (same(dx, 0), same(0, dy));
+                if (!row && !col) || (row && col && !same(dx, dy)) {
+                    region.sides += 1;
+                }
+            }
+        }
+    }
+    stats
+}
+
+fn part1(map: &[Vec<char>]) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = region_stats(map, &label_regions(map))
+        .iter()
+        .map(|r| r.area * r.perimeter)
+        .sum();
+
+    println!("part1: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+fn part2(map: &[Vec<char>]) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = region_stats(map, &label_regions(map))
+        .iter()
+        .map(|r| r.area * r.sides)
+        .sum();
+
+    println!("part2: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+// walks with an explicit stack so a single huge region cannot overflow the call stack
 fn dfs(x: usize, y: usize, map: &[Vec<char>], area: &mut HashSet<(isize, isize)>) -> usize {
-    if area.insert((x as isize, y as isize)) {
-        let mut perimeter = 0;
+    let mut perimeter = 0;
+    let mut stack = vec![(x, y)];
+    while let Some((x, y)) = stack.pop() {
+        if !area.insert((x as isize, y as isize)) {
+            continue;
+        }
         for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
             if (x == 0 && dx == -1)
                 || (x + 1 == map.len() && dx == 1)
@@ -33,19 +183,16 @@ fn dfs(x: usize, y: usize, map: &[Vec<char>], area: &mut HashSet<(isize, isize)>
             }
             let (nx, ny) = ((x as isize + dx) as usize, (y as isize + dy) as usize);
             if map[nx][ny] == map[x][y] {
-                let p = dfs(nx, ny, map, area);
-                perimeter += p;
+                stack.push((nx, ny));
             } else {
                 perimeter += 1;
             }
         }
-        perimeter
-    } else {
-        0
     }
+    perimeter
 }
 
-fn part1(map: &[Vec<char>]) -> Result<usize> {
+fn part1_dfs(map: &[Vec<char>]) -> Result<usize> {
     let _start = Instant::now();
 
     let mut result = 0;
@@ -61,7 +208,7 @@ fn part1(map: &[Vec<char>]) -> Result<usize> {
         }
     }
 
-    println!("part1: {result}");
+    println!("part1 with dfs: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
@@ -102,7 +249,7 @@ fn number_of_side(area: &HashSet<(isize, isize)>) -> usize {
     count
 }
 
-fn part2(map: &[Vec<char>]) -> Result<usize> {
+fn part2_dfs(map: &[Vec<char>]) -> Result<usize> {
     let _start = Instant::now();
 
     let mut result = 0;
@@ -118,7 +265,7 @@ fn part2(map: &[Vec<char>]) -> Result<usize> {
         }
     }
 
-    println!("part2: {result}");
+    println!("part2 with dfs: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
@@ -186,6 +333,217 @@ fn part2_count_corner(map: &[Vec<char>]) -> Result<usize> {
     Ok(result)
 }
 
+type Point = (usize, usize);
+
+// a closed ring of lattice points, cells are unit squares with (row, col) as their top left
+// corner, rings run clockwise on screen around the region and counterclockwise around holes
+type Ring = Vec<Point>;
+
+#[derive(Debug, Clone)]
+struct Region {
+    plant: char,
+    area: usize,
+    outer: Ring,
+    holes: Vec<Ring>,
+}
+
+impl Region {
+    fn rings(&self) -> impl Iterator<Item = &Ring> {
+        std::iter::once(&self.outer).chain(&self.holes)
+    }
+
+    fn perimeter(&self) -> usize {
+        self.rings()
+            .map(|ring| {
+                ring.iter()
+                    .zip(ring.iter().cycle().skip(1))
+                    .map(|(a, b)| a.0.abs_diff(b.0) + a.1.abs_diff(b.1))
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    // every ring vertex is a turn, so it starts a new side
+    fn sides(&self) -> usize {
+        self.rings().map(|ring| ring.len()).sum()
+    }
+
+    fn price(&self) -> usize {
+        self.area * self.perimeter()
+    }
+
+    fn bulk_price(&self) -> usize {
+        self.area * self.sides()
+    }
+}
+
+// twice the signed area, positive for clockwise rings on screen
+fn ring_area2(ring: &Ring) -> isize {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a.1 as isize * b.0 as isize - b.1 as isize * a.0 as isize)
+        .sum()
+}
+
+// directions in clockwise order, turning right is the next one and left the previous one
+const NORTH: usize = 0;
+const EAST: usize = 1;
+const SOUTH: usize = 2;
+const WEST: usize = 3;
+
+fn step(p: Point, dir: usize) -> Point {
+    match dir {
+        NORTH => (p.0 - 1, p.1),
+        EAST => (p.0, p.1 + 1),
+        SOUTH => (p.0 + 1, p.1),
+        _ => (p.0, p.1 - 1),
+    }
+}
+
+// walks the fence with the region on its right hand side; where two cells of the region
+// only touch diagonally the walk turns left first, so it hugs the cell outside the region
+// and every ring stays simple
+fn trace_rings(cells: &HashSet<(isize, isize)>) -> Vec<Ring> {
+    let inside = |r: usize, c: usize, dr: isize, dc: isize| {
+        cells.contains(&(r as isize + dr, c as isize + dc))
+    };
+    let mut edges: HashMap<Point, Vec<usize>> = HashMap::new();
+    for &(r, c) in cells {
+        let (r, c) = (r as usize, c as usize);
+        if !inside(r, c, -1, 0) {
+            edges.entry((r, c)).or_default().push(EAST);
+        }
+        if !inside(r, c, 0, 1) {
+            edges.entry((r, c + 1)).or_default().push(SOUTH);
+        }
+        if !inside(r, c, 1, 0) {
+            edges.entry((r + 1, c + 1)).or_default().push(WEST);
+        }
+        if !inside(r, c, 0, -1) {
+            edges.entry((r + 1, c)).or_default().push(NORTH);
+        }
+    }
+    // edges are only ever removed, so the smallest point with edges left only moves forward
+    let mut starts: Vec<Point> = edges.keys().cloned().collect();
+    starts.sort();
+    let mut cursor = 0;
+    let mut rings = vec![];
+    loop {
+        while cursor < starts.len() && edges[&starts[cursor]].is_empty() {
+            cursor += 1;
+        }
+        let Some(&start) = starts.get(cursor) else {
+            break;
+        };
+        let mut dir = edges.get_mut(&start).unwrap().pop().unwrap();
+        let first = dir;
+        let mut ring = vec![];
+        let mut p = step(start, dir);
+        while p != start {
+            let out = edges.get_mut(&p).unwrap();
+            let i = [3, 0, 1]
+                .iter()
+                .find_map(|turn| out.iter().position(|&d| d == (dir + turn) % 4))
+                .unwrap();
+            let next = out.swap_remove(i);
+            if next != dir {
+                ring.push(p);
+            }
+            dir = next;
+            p = step(p, dir);
+        }
+        if first != dir {
+            ring.insert(0, start);
+        }
+        rings.push(ring);
+    }
+    rings
+}
+
+fn trace_regions(map: &[Vec<char>]) -> Vec<Region> {
+    let labels = label_regions(map);
+    let mut cells: Vec<HashSet<(isize, isize)>> = vec![HashSet::new(); labels.count];
+    let mut plants = vec![' '; labels.count];
+    for (k, &label) in labels.grid.iter().enumerate() {
+        let (i, j) = (k / labels.width, k % labels.width);
+        cells[label].insert((i as isize, j as isize));
+        plants[label] = map[i][j];
+    }
+    cells
+        .into_iter()
+        .zip(plants)
+        .map(|(area, plant)| {
+            let (outer, holes): (Vec<_>, Vec<_>) = trace_rings(&area)
+                .into_iter()
+                .partition(|r| ring_area2(r) > 0);
+            Region {
+                plant,
+                area: area.len(),
+                outer: outer.into_iter().next().unwrap(),
+                holes,
+            }
+        })
+        .collect()
+}
+
+fn part2_polygons(map: &[Vec<char>]) -> Result<usize> {
+    let _start = Instant::now();
+
+    let regions = trace_regions(map);
+    let result = regions.iter().map(|r| r.bulk_price()).sum();
+
+    println!("part2 by polygons: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+fn to_svg(map: &[Vec<char>], regions: &[Region], scale: usize) -> String {
+    let (height, width) = (map.len() * scale, map[0].len() * scale);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+    );
+    for (i, region) in regions.iter().enumerate() {
+        let mut d = String::new();
+        for ring in region.rings() {
+            for (k, &(r, c)) in ring.iter().enumerate() {
+                d += &format!(
+                    "{}{} {} ",
+                    if k == 0 { "M" } else { "L" },
+                    c * scale,
+                    r * scale
+                );
+            }
+            d += "Z ";
+        }
+        // golden angle steps keep neighbouring regions apart in hue
+        let hue = i * 137 % 360;
+        svg += &format!(
+            "  <path d=\"{}\" fill=\"hsl({hue},60%,70%)\" fill-rule=\"evenodd\" stroke=\"black\">\
+             <title>{} area {} perimeter {} sides {} price {}</title></path>\n",
+            d.trim_end(),
+            region.plant,
+            region.area,
+            region.perimeter(),
+            region.sides(),
+            region.price()
+        );
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+// `--svg <path>` writes every region outline, holes included, to an svg file
+fn export_svg(map: &[Vec<char>], args: &[String]) -> Result<()> {
+    let Some(i) = args.iter().position(|a| a == "--svg") else {
+        return Ok(());
+    };
+    let Some(path) = args.get(i + 1) else {
+        return err!("--svg needs an output path");
+    };
+    std::fs::write(path, to_svg(map, &trace_regions(map), 10))?;
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
@@ -193,7 +551,11 @@ fn main() -> Result<()> {
     let map = parse_input(input)?;
     part1(&map)?;
     part2(&map)?;
+    part1_dfs(&map)?;
+    part2_dfs(&map)?;
     part2_count_corner(&map)?;
+    part2_polygons(&map)?;
+    export_svg(&map, &std::env::args().collect::<Vec<_>>())?;
     Ok(())
 }
 
@@ -205,8 +567,11 @@ BBCC
 EEEC";
     let map = parse_input(input)?;
     assert_eq!(part1(&map)?, 140);
+    assert_eq!(part1_dfs(&map)?, 140);
     assert_eq!(part2(&map)?, 80);
+    assert_eq!(part2_dfs(&map)?, 80);
     assert_eq!(part2_count_corner(&map)?, 80);
+    assert_eq!(part2_polygons(&map)?, 80);
     Ok(())
 }
 
@@ -219,8 +584,11 @@ OXOXO
 OOOOO";
     let map = parse_input(input)?;
     assert_eq!(part1(&map)?, 772);
+    assert_eq!(part1_dfs(&map)?, 772);
     assert_eq!(part2(&map)?, 436);
+    assert_eq!(part2_dfs(&map)?, 436);
     assert_eq!(part2_count_corner(&map)?, 436);
+    assert_eq!(part2_polygons(&map)?, 436);
     Ok(())
 }
 
@@ -238,8 +606,11 @@ MIIISIJEEE
 MMMISSJEEE";
     let map = parse_input(input)?;
     assert_eq!(part1(&map)?, 1930);
+    assert_eq!(part1_dfs(&map)?, 1930);
     assert_eq!(part2(&map)?, 1206);
+    assert_eq!(part2_dfs(&map)?, 1206);
     assert_eq!(part2_count_corner(&map)?, 1206);
+    assert_eq!(part2_polygons(&map)?, 1206);
     Ok(())
 }
 
@@ -252,7 +623,9 @@ EXXXX
 EEEEE";
     let map = parse_input(input)?;
     assert_eq!(part2(&map)?, 236);
+    assert_eq!(part2_dfs(&map)?, 236);
     assert_eq!(part2_count_corner(&map)?, 236);
+    assert_eq!(part2_polygons(&map)?, 236);
     Ok(())
 }
 
@@ -261,7 +634,9 @@ fn example_input4() -> Result<()> {
     let input = "AAAA";
     let map = parse_input(input)?;
     assert_eq!(part2(&map)?, 16);
+    assert_eq!(part2_dfs(&map)?, 16);
     assert_eq!(part2_count_corner(&map)?, 16);
+    assert_eq!(part2_polygons(&map)?, 16);
     Ok(())
 }
 
@@ -273,7 +648,9 @@ A
 A";
     let map = parse_input(input)?;
     assert_eq!(part2(&map)?, 16);
+    assert_eq!(part2_dfs(&map)?, 16);
     assert_eq!(part2_count_corner(&map)?, 16);
+    assert_eq!(part2_polygons(&map)?, 16);
     Ok(())
 }
 #[test]
@@ -285,7 +662,9 @@ OXO
 OOO";
     let map = parse_input(input)?;
     assert_eq!(part2(&map)?, 164);
+    assert_eq!(part2_dfs(&map)?, 164);
     assert_eq!(part2_count_corner(&map)?, 164);
+    assert_eq!(part2_polygons(&map)?, 164);
     Ok(())
 }
 
@@ -294,7 +673,100 @@ fn real_input() -> Result<()> {
     let input = std::fs::read_to_string("input/input.txt").unwrap();
     let map = parse_input(input)?;
     assert_eq!(part1(&map)?, 1494342);
+    assert_eq!(part1_dfs(&map)?, 1494342);
     assert_eq!(part2(&map)?, 893676);
+    assert_eq!(part2_dfs(&map)?, 893676);
     assert_eq!(part2_count_corner(&map)?, 893676);
+    assert_eq!(part2_polygons(&map)?, 893676);
+    Ok(())
+}
+
+#[test]
+fn region_polygons() -> Result<()> {
+    let input = "OOOOO
+OXOXO
+OOOOO
+OXOXO
+OOOOO";
+    let map = parse_input(input)?;
+    let regions = trace_regions(&map);
+    assert_eq!(regions.len(), 5);
+    let o = &regions[0];
+    assert_eq!((o.plant, o.area, o.holes.len()), ('O', 21, 4));
+    assert_eq!(o.outer, vec![(0, 0), (0, 5), (5, 5), (5, 0)]);
+    assert_eq!(o.holes[0], vec![(1, 1), (2, 1), (2, 2), (1, 2)]);
+    assert_eq!((o.perimeter(), o.sides(), o.price()), (36, 20, 756));
+    assert_eq!(regions.iter().map(|r| r.price()).sum::<usize>(), 772);
+
+    let svg = to_svg(&map, &regions, 10);
+    assert_eq!(svg.matches("<path").count(), 5);
+    assert!(svg.contains("M0 0 L50 0 L50 50 L0 50 Z M10 10 L10 20 L20 20 L20 10 Z"));
+
+    // the B cells touch diagonally inside the A ring, each turn there starts a side
+    let map = parse_input("AAAA\nABAA\nAABA\nAAAA")?;
+    let a = &trace_regions(&map)[0];
+    assert_eq!(
+        (a.area, a.holes.len(), a.perimeter(), a.sides()),
+        (14, 2, 24, 12)
+    );
+    assert_eq!(part2_polygons(&map)?, part2_count_corner(&map)?);
+    Ok(())
+}
+
+#[test]
+fn label_grid() -> Result<()> {
+    let input = "RRRRIICCFF
+RRRRIICCCF
+VVRRRCCFFF
+VVRCCCJFFF
+VVVVCJJCFE
+VVIVCCJJEE
+VVIIICJJEE
+MIIIIIJJEE
+MIIISIJEEE
+MMMISSJEEE";
+    let map = parse_input(input)?;
+    let labels = label_regions(&map);
+    assert_eq!(labels.count, 11);
+    assert_eq!(&labels.grid[..10], &[0, 0, 0, 0, 1, 1, 2, 2, 3, 3]);
+    let stats = region_stats(&map, &labels);
+    let r = &stats[0];
+    assert_eq!((r.plant, r.area, r.perimeter, r.sides), ('R', 12, 18, 10));
+    for (stats, region) in stats.iter().zip(trace_regions(&map)) {
+        assert_eq!(stats.perimeter, region.perimeter());
+        assert_eq!(stats.sides, region.sides());
+    }
+
+    // a single plant with a checkerboard pocket, deep enough to overflow a recursive walk
+    let n = 2000;
+    let mut map = vec![vec!['A'; n]; n];
+    map[1][1] = 'B';
+    map[2][2] = 'B';
+    let labels = label_regions(&map);
+    assert_eq!(labels.count, 3);
+    let stats = region_stats(&map, &labels);
+    assert_eq!(stats[0].area, n * n - 2);
+    assert_eq!(stats[0].perimeter, 4 * n + 8);
+    assert_eq!(stats[0].sides, 4 + 8);
+
+    // every solver main runs agrees on a smaller copy of it
+    let n = 200;
+    let mut map = vec![vec!['A'; n]; n];
+    map[1][1] = 'B';
+    map[2][2] = 'B';
+    let price = (n * n - 2) * (4 * n + 8) + 2 * 4;
+    let discount = (n * n - 2) * 12 + 2 * 4;
+    assert_eq!(part1(&map)?, price);
+    assert_eq!(part2(&map)?, discount);
+    assert_eq!(part1_dfs(&map)?, price);
+    assert_eq!(part2_dfs(&map)?, discount);
+    assert_eq!(part2_count_corner(&map)?, discount);
+    assert_eq!(part2_polygons(&map)?, discount);
+    assert_eq!(
+        to_svg(&map, &trace_regions(&map), 10)
+            .matches("<path")
+            .count(),
+        3
+    );
     Ok(())
 }
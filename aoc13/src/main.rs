@@ -109,14 +109,44 @@ fn part1(machines: &[Machine]) -> Result<i64> {
     Ok(result)
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Pricing {
+    cost_a: i64,
+    cost_b: i64,
+    // most presses allowed on each button
+    max_presses: Option<i64>,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Pricing {
+            cost_a: 3,
+            cost_b: 1,
+            max_presses: None,
+        }
+    }
+}
+
+impl Pricing {
+    fn cost(&self, (a, b): (i64, i64)) -> i64 {
+        a * self.cost_a + b * self.cost_b
+    }
+
+    fn allows(&self, (a, b): (i64, i64)) -> bool {
+        a >= 0 && b >= 0 && self.max_presses.is_none_or(|max| a <= max && b <= max)
+    }
+}
+
 fn part1_with_math(machines: &[Machine]) -> Result<i64> {
     let _start = Instant::now();
 
-    let result = machines
-        .iter()
-        .filter_map(|m| solve(m.prize, m.button_a, m.button_b))
-        .map(|(a, b)| a * 3 + b)
-        .sum();
+    let pricing = Pricing::default();
+    let mut result = 0;
+    for m in machines {
+        if let Some(presses) = solve(m.prize, m.button_a, m.button_b, &pricing)? {
+            result += pricing.cost(presses);
+        }
+    }
 
     println!("part1 with math: {result}");
 
@@ -124,21 +154,102 @@ fn part1_with_math(machines: &[Machine]) -> Result<i64> {
     Ok(result)
 }
 
-fn solve(p: (i64, i64), a: (i64, i64), b: (i64, i64)) -> Option<(i64, i64)> {
+fn cross(u: (i64, i64), v: (i64, i64)) -> Result<i64> {
+    u.0.checked_mul(v.1)
+        .zip(u.1.checked_mul(v.0))
+        .and_then(|(a, b)| a.checked_sub(b))
+        .ok_or_else(|| format!("overflow computing {u:?} x {v:?}").into())
+}
+
+// the cheapest presses (a, b) with a * A + b * B = P, or None when P is out of reach
+fn solve(
+    p: (i64, i64),
+    a: (i64, i64),
+    b: (i64, i64),
+    pricing: &Pricing,
+) -> Result<Option<(i64, i64)>> {
     // p.0 = a.0 * x + b.0 * y
     // p.1 = a.1 * x + b.1 * y
     // p.0 * b.1 = a.0 * b.1 * x + b.0 * b.1 * y
     // p.1 * b.0 = a.1 * b.0 * x + b.0 * b.1 * y
     // p.0 * b.1 - p.1 * b. 0 = (a.0 * b.1 - a.1 * b.0)x
     // x = (p.0 * b.1 - p.1 * b.0) / (a.0 * b.1 - a.1 * b.0)
-    let x = (p.0 * b.1 - p.1 * b.0, a.0 * b.1 - a.1 * b.0);
-    let y = (p.0 * a.1 - p.1 * a.0, b.0 * a.1 - b.1 * a.0);
-    assert_ne!(x.1, 0);
-    assert_ne!(y.1, 0);
-    if x.0 % x.1 == 0 && y.0 % y.1 == 0 {
-        Some((x.0 / x.1, y.0 / y.1))
+    let det = cross(a, b)?;
+    if det != 0 {
+        let (x, y) = (cross(p, b)?, cross(a, p)?);
+        if x % det != 0 || y % det != 0 {
+            return Ok(None);
+        }
+        let presses = (x / det, y / det);
+        return Ok(pricing.allows(presses).then_some(presses));
+    }
+    // collinear buttons only reach points on their shared line,
+    // the line is followed along an axis it is not parallel to
+    let dir = if a != (0, 0) { a } else { b };
+    if dir == (0, 0) {
+        return Ok((p == (0, 0)).then_some((0, 0)));
+    }
+    if cross(dir, p)? != 0 {
+        return Ok(None);
+    }
+    let (a, b, p) = if dir.0 != 0 {
+        (a.0, b.0, p.0)
     } else {
-        None
+        (a.1, b.1, p.1)
+    };
+    solve_line(a, b, p, pricing)
+}
+
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a.signum() * a, a.signum(), 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+// a * x + b * y = p: with g = gcd(a, b) every solution is x0 + t * b / g, y0 - t * a / g,
+// the cost is linear in t so the cheapest one sits at an end of the allowed range of t
+fn solve_line(a: i64, b: i64, p: i64, pricing: &Pricing) -> Result<Option<(i64, i64)>> {
+    let (a, b, p) = (a as i128, b as i128, p as i128);
+    let (g, x, y) = extended_gcd(a, b);
+    if p % g != 0 {
+        return Ok(None);
+    }
+    let (x0, y0, dx, dy) = (x * (p / g), y * (p / g), b / g, -a / g);
+
+    // narrows lo..=hi to the t with c + d * t >= 0
+    let (mut lo, mut hi): (Option<i128>, Option<i128>) = (None, None);
+    let mut feasible = true;
+    let mut bound = |c: i128, d: i128| match d.signum() {
+        0 => feasible &= c >= 0,
+        1 => lo = lo.max(Some(-c.div_euclid(d))),
+        _ => hi = Some(hi.map_or(c.div_euclid(-d), |h| h.min(c.div_euclid(-d)))),
+    };
+    bound(x0, dx);
+    bound(y0, dy);
+    if let Some(max) = pricing.max_presses {
+        let max = max as i128;
+        bound(max - x0, -dx);
+        bound(max - y0, -dy);
+    }
+    if !feasible || lo.zip(hi).is_some_and(|(lo, hi)| lo > hi) {
+        return Ok(None);
+    }
+    let slope = pricing.cost_a as i128 * dx + pricing.cost_b as i128 * dy;
+    let t = match slope.signum() {
+        1 => lo,
+        -1 => hi,
+        _ => hi.or(lo),
+    };
+    let Some(t) = t else {
+        return err!("presses are unbounded for {a} * x + {b} * y = {p}");
+    };
+    let presses = (x0 + dx * t, y0 + dy * t);
+    match (i64::try_from(presses.0), i64::try_from(presses.1)) {
+        (Ok(x), Ok(y)) => Ok(Some((x, y))),
+        _ => err!("overflow in presses {presses:?}"),
     }
 }
 
@@ -147,21 +258,50 @@ fn part2(machines: &[Machine]) -> Result<i64> {
 
     let offset = 10000000000000;
 
-    let result: i64 = machines
-        .iter()
-        .filter_map(|m| {
-            solve(
-                (m.prize.0 + offset, m.prize.1 + offset),
-                m.button_a,
-                m.button_b,
-            )
-        })
-        .map(|(a, b)| a * 3 + b)
-        .sum();
+    let pricing = Pricing::default();
+    let mut result = 0;
+    for m in machines {
+        let prize = (m.prize.0 + offset, m.prize.1 + offset);
+        if let Some(presses) = solve(prize, m.button_a, m.button_b, &pricing)? {
+            result += pricing.cost(presses);
+        }
+    }
     println!("part2: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
+
+// `--cost-a <n>`, `--cost-b <n>`, `--max-presses <n>` and `--offset <n>` change the pricing
+fn custom_pricing(machines: &[Machine], args: &[String]) -> Result<i64> {
+    let _start = Instant::now();
+
+    let mut pricing = Pricing::default();
+    let mut offset = 0;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let Some(value) = args.next() else {
+            return err!("{flag} needs a value");
+        };
+        match flag.as_str() {
+            "--cost-a" => pricing.cost_a = value.parse()?,
+            "--cost-b" => pricing.cost_b = value.parse()?,
+            "--max-presses" => pricing.max_presses = Some(value.parse()?),
+            "--offset" => offset = value.parse()?,
+            _ => return err!("unknown flag: {flag:?}"),
+        }
+    }
+    let mut result = 0;
+    for m in machines {
+        let prize = (m.prize.0 + offset, m.prize.1 + offset);
+        if let Some(presses) = solve(prize, m.button_a, m.button_b, &pricing)? {
+            result += pricing.cost(presses);
+        }
+    }
+    println!("custom pricing {pricing:?} with offset {offset}: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
@@ -171,6 +311,7 @@ fn main() -> Result<()> {
     part1(&machines)?;
     part1_with_math(&machines)?;
     part2(&machines)?;
+    custom_pricing(&machines, &std::env::args().skip(1).collect::<Vec<_>>())?;
     Ok(())
 }
 
@@ -197,6 +338,14 @@ Prize: X=18641, Y=10279
     assert_eq!(part1(&machines)?, 480);
     assert_eq!(part1_with_math(&machines)?, 480);
     assert_eq!(part2(&machines)?, 875318608908);
+    let args = ["--max-presses", "100"].map(String::from);
+    assert_eq!(custom_pricing(&machines, &args)?, 480);
+    let args = ["--offset", "10000000000000"].map(String::from);
+    assert_eq!(custom_pricing(&machines, &args)?, 875318608908);
+    for bad in [&["--max-press", "100"][..], &["--offset"]] {
+        let args: Vec<_> = bad.iter().map(|a| a.to_string()).collect();
+        assert!(custom_pricing(&machines, &args).is_err(), "{bad:?}");
+    }
     Ok(())
 }
 
@@ -211,3 +360,64 @@ fn real_input() -> Result<()> {
     assert_eq!(2, 2);
     Ok(())
 }
+
+#[test]
+fn degenerate_machines() -> Result<()> {
+    let pricing = Pricing::default();
+    // x + 3y = 10 along the diagonal
+    assert_eq!(solve((10, 10), (1, 1), (3, 3), &pricing)?, Some((1, 3)));
+    let pricing = Pricing {
+        cost_a: 1,
+        cost_b: 5,
+        max_presses: None,
+    };
+    assert_eq!(solve((10, 10), (1, 1), (3, 3), &pricing)?, Some((10, 0)));
+    let limited = Pricing {
+        max_presses: Some(5),
+        ..pricing
+    };
+    assert_eq!(solve((10, 10), (1, 1), (3, 3), &limited)?, Some((4, 2)));
+    assert_eq!(
+        solve(
+            (10, 10),
+            (1, 1),
+            (3, 3),
+            &Pricing {
+                max_presses: Some(0),
+                ..pricing
+            }
+        )?,
+        None
+    );
+
+    assert_eq!(solve((10, 11), (1, 1), (3, 3), &pricing)?, None);
+    assert_eq!(solve((5, 5), (2, 2), (4, 4), &pricing)?, None);
+    assert_eq!(
+        solve((0, 12), (0, 2), (0, 3), &Pricing::default())?,
+        Some((0, 4))
+    );
+    assert_eq!(
+        solve((12, 0), (0, 0), (4, 0), &Pricing::default())?,
+        Some((0, 3))
+    );
+    assert_eq!(
+        solve((0, 0), (0, 0), (0, 0), &Pricing::default())?,
+        Some((0, 0))
+    );
+
+    let offset = 10000000000000;
+    let presses = solve((offset, offset), (1, 1), (3, 3), &Pricing::default())?;
+    assert_eq!(presses, Some((1, 3333333333333)));
+
+    assert!(solve((i64::MAX / 10, 1), (94, 34), (22, 67), &Pricing::default()).is_err());
+
+    // -x + 2y = 0 only bounds t from below, a rebate on A makes it endlessly cheaper
+    let rebate = Pricing {
+        cost_a: -1,
+        cost_b: 0,
+        max_presses: None,
+    };
+    assert!(solve((0, 0), (-1, -1), (2, 2), &rebate).is_err());
+    assert!(solve((0, 0), (-1, -1), (2, 2), &Pricing::default()).is_ok());
+    Ok(())
+}
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::io::{self, Read};
+use std::ops::Range;
+use std::path::Path;
 use std::time::Instant;
 
 #[allow(unused_macros)]
@@ -54,9 +57,12 @@ fn move_robots(robots: &[(Coord, Coord)], secs: usize, bound: Coord) -> usize {
         }
     }
 
+    safety_factor(robots.iter().map(|&(p, _)| p), bound)
+}
+
+fn safety_factor(positions: impl Iterator<Item = Coord>, bound: Coord) -> usize {
     let mut quadrant = [0; 4];
-    for (p, _) in robots {
-        let (x, y) = p;
+    for (x, y) in positions {
         if x == bound.0 / 2 || y == bound.1 / 2 {
             continue;
         }
@@ -70,32 +76,102 @@ fn move_robots(robots: &[(Coord, Coord)], secs: usize, bound: Coord) -> usize {
     quadrant.into_iter().product()
 }
 
-fn display_robots(robots: &[(Coord, Coord)], bound: Coord) {
-    let mut grid = vec![vec!['.'; bound.1 as usize]; bound.0 as usize];
+fn render_robots(robots: &[(Coord, Coord)], bound: Coord) -> String {
+    let mut grid = vec![vec!['.'; bound.0 as usize]; bound.1 as usize];
     for (p, _) in robots {
-        grid[p.0 as usize][p.1 as usize] = '*';
+        grid[p.1 as usize][p.0 as usize] = '*';
     }
-    for i in 0..bound.1 as usize {
-        (0..bound.0 as usize).for_each(|j| {
-            print!("{}", grid[j][i]);
-        });
-        println!()
+    grid.iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+fn display_robots(robots: &[(Coord, Coord)], bound: Coord) {
+    print!("{}", render_robots(robots, bound));
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+#[derive(Debug, Clone)]
+struct RobotField {
+    robots: Vec<(Coord, Coord)>,
+    bound: Coord,
+}
+
+impl RobotField {
+    fn new(robots: &[(Coord, Coord)], bound: Coord) -> Self {
+        RobotField {
+            robots: robots.to_vec(),
+            bound,
+        }
+    }
+
+    // p + v * t mod bound, with t reduced by the bound first so any t fits
+    fn position(&self, (p, v): (Coord, Coord), t: u64) -> Coord {
+        let at = |p: i64, v: i64, bound: i64| {
+            let t = (t % bound as u64) as i64;
+            (p + v.rem_euclid(bound) * t).rem_euclid(bound)
+        };
+        (at(p.0, v.0, self.bound.0), at(p.1, v.1, self.bound.1))
     }
+
+    fn positions_at(&self, t: u64) -> Vec<Coord> {
+        self.robots.iter().map(|&r| self.position(r, t)).collect()
+    }
+
+    fn robots_at(&self, t: u64) -> Vec<(Coord, Coord)> {
+        self.robots
+            .iter()
+            .map(|&r| (self.position(r, t), r.1))
+            .collect()
+    }
+
+    fn safety_factor(&self, t: u64) -> usize {
+        safety_factor(self.positions_at(t).into_iter(), self.bound)
+    }
+
+    // seconds until each robot is back where it started
+    fn cycle_lengths(&self) -> Vec<u64> {
+        let period = |v: i64, bound: i64| bound / gcd(v, bound);
+        self.robots
+            .iter()
+            .map(|&(_, v)| {
+                let (x, y) = (period(v.0, self.bound.0), period(v.1, self.bound.1));
+                (x / gcd(x, y) * y) as u64
+            })
+            .collect()
+    }
+}
+
+fn part1(robots: &[(Coord, Coord)], bound: Coord) -> Result<usize> {
+    let _start = Instant::now();
+
+    let field = RobotField::new(robots, bound);
+    let result = field.safety_factor(100);
+    let longest = field.cycle_lengths().into_iter().max().unwrap_or(0);
+    println!("part1: {result}, robots repeat within {longest} seconds");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
 }
 
-fn part1(robots: &[(Coord, Coord)]) -> Result<usize> {
+fn part1_stepping(robots: &[(Coord, Coord)], bound: Coord) -> Result<usize> {
     let _start = Instant::now();
 
-    let result = move_robots(robots, 100, (101, 103));
-    println!("part1: {result}");
+    let result = move_robots(robots, 100, bound);
+    println!("part1 with stepping: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
 
+#[cfg(test)]
 fn dis(a: Coord, b: Coord) -> usize {
     (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as usize
 }
 
+#[cfg(test)]
 fn total_dis(robots: &[(Coord, Coord)]) -> usize {
     let mut d = 0;
     for i in 0..robots.len() {
@@ -106,7 +182,9 @@ fn total_dis(robots: &[(Coord, Coord)]) -> usize {
     d
 }
 
-fn part2(robots: &[(Coord, Coord)]) -> Result<usize> {
+// slow and prints every closer frame, kept as a cross-check in tests
+#[cfg(test)]
+fn part2_min_distance(robots: &[(Coord, Coord)], bound: Coord) -> Result<usize> {
     let _start = Instant::now();
 
     let mut robots = robots.to_vec();
@@ -115,13 +193,13 @@ fn part2(robots: &[(Coord, Coord)]) -> Result<usize> {
     let max_dur = 5000;
     for i in 1.. {
         for robot in &mut robots {
-            moving(robot, (101, 103));
+            moving(robot, bound);
         }
         let d = total_dis(&robots);
         if d < min_dis {
             min_dis = d;
             println!("seconds: {i}");
-            display_robots(&robots, (101, 103));
+            display_robots(&robots, bound);
             last_sec = i;
         }
         if i - last_sec > max_dur {
@@ -130,18 +208,347 @@ fn part2(robots: &[(Coord, Coord)]) -> Result<usize> {
         }
     }
 
-    println!("part2: {last_sec}");
+    println!("part2 with min distance: {last_sec}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(last_sec)
 }
 
+#[derive(Debug, Clone)]
+struct TreeFrame {
+    second: usize,
+    // how many standard deviations the tighter axis stands out from the other seconds,
+    // the looser of the two axes is reported
+    confidence: f64,
+    frame: String,
+}
+
+fn variance(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let n = values.clone().count() as f64;
+    let mean = values.clone().sum::<f64>() / n;
+    values.map(|v| (v - mean).powi(2)).sum::<f64>() / n
+}
+
+// the second within one period with the least spread along an axis, and how far it stands out
+fn tightest_second(variances: &[f64]) -> (usize, f64) {
+    let (t, &min) = variances
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap();
+    let mean = variances.iter().sum::<f64>() / variances.len() as f64;
+    let sd = variance(variances.iter().cloned()).sqrt();
+    (t, if sd > 0.0 { (mean - min) / sd } else { 0.0 })
+}
+
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+// the smallest t >= 0 with t = r0 mod m0 and t = r1 mod m1
+fn crt((r0, m0): (i64, i64), (r1, m1): (i64, i64)) -> Result<i64> {
+    let (g, p, _) = extended_gcd(m0, m1);
+    if (r1 - r0) % g != 0 {
+        return err!("no t with t = {r0} mod {m0} and t = {r1} mod {m1}");
+    }
+    let lcm = m0 / g * m1;
+    let k = ((r1 - r0) / g * p).rem_euclid(m1 / g);
+    Ok((r0 + m0 * k).rem_euclid(lcm))
+}
+
+// x positions repeat every bound.0 seconds and y positions every bound.1 seconds,
+// so each axis only needs one period of positions to find its tightest second
+fn find_tree(field: &RobotField) -> Result<TreeFrame> {
+    let bound = field.bound;
+    let mut x_variances = vec![];
+    let mut y_variances = vec![];
+    for t in 0..bound.0.max(bound.1) {
+        let positions = field.positions_at(t as u64);
+        if t < bound.0 {
+            x_variances.push(variance(positions.iter().map(|p| p.0 as f64)));
+        }
+        if t < bound.1 {
+            y_variances.push(variance(positions.iter().map(|p| p.1 as f64)));
+        }
+    }
+    let (tx, cx) = tightest_second(&x_variances);
+    let (ty, cy) = tightest_second(&y_variances);
+    let second = crt((tx as i64, bound.0), (ty as i64, bound.1))? as usize;
+
+    Ok(TreeFrame {
+        second,
+        confidence: cx.min(cy),
+        frame: render_robots(&field.robots_at(second as u64), bound),
+    })
+}
+
+fn part2(robots: &[(Coord, Coord)], bound: Coord) -> Result<usize> {
+    let _start = Instant::now();
+
+    let tree = find_tree(&RobotField::new(robots, bound))?;
+    print!("{}", tree.frame);
+    println!("confidence: {:.1}", tree.confidence);
+    let result = tree.second;
+    println!("part2: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+// `--bound <width>,<height>` changes the room size
+fn bound_from_args(args: &[String]) -> Result<Coord> {
+    let Some(i) = args.iter().position(|a| a == "--bound") else {
+        return Ok((101, 103));
+    };
+    match args.get(i + 1).and_then(|b| b.split_once(',')) {
+        Some((w, h)) => Ok((w.trim().parse()?, h.trim().parse()?)),
+        None => err!("--bound expects <width>,<height>"),
+    }
+}
+
+// robots per cell at second t, rows are y
+fn density(field: &RobotField, t: u64) -> Vec<Vec<u32>> {
+    let mut grid = vec![vec![0; field.bound.0 as usize]; field.bound.1 as usize];
+    for (x, y) in field.positions_at(t) {
+        grid[y as usize][x as usize] += 1;
+    }
+    grid
+}
+
+// plain PBM, 1 is black
+fn to_pbm(field: &RobotField, t: u64) -> String {
+    let mut s = format!("P1\n# second {t}\n{} {}\n", field.bound.0, field.bound.1);
+    for row in density(field, t) {
+        let row: Vec<_> = row.iter().map(|&n| if n > 0 { "1" } else { "0" }).collect();
+        s += &row.join(" ");
+        s.push('\n');
+    }
+    s
+}
+
+// plain PGM on a white background, cells get darker with every robot on them
+fn to_pgm(field: &RobotField, t: u64) -> String {
+    let grid = density(field, t);
+    let max = grid.iter().flatten().cloned().max().unwrap_or(0).max(1);
+    let mut s = format!(
+        "P2\n# second {t}\n{} {}\n{max}\n",
+        field.bound.0, field.bound.1
+    );
+    for row in grid {
+        let row: Vec<_> = row.iter().map(|&n| (max - n).to_string()).collect();
+        s += &row.join(" ");
+        s.push('\n');
+    }
+    s
+}
+
+// packs codes least significant bit first, as GIF expects
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u8) {
+        self.acc |= (code as u32) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+// variable width LZW as used by GIF, the table is reset with a clear code once it
+// holds 4096 entries
+fn lzw_encode(pixels: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear: u16 = 1 << min_code_size;
+    let end = clear + 1;
+    let mut out = BitWriter {
+        bytes: vec![],
+        acc: 0,
+        bits: 0,
+    };
+    let mut width = min_code_size + 1;
+    let mut next = end + 1;
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    out.write(clear, width);
+    let Some((&first, rest)) = pixels.split_first() else {
+        out.write(end, width);
+        return out.finish();
+    };
+    let mut prefix = first as u16;
+    for &k in rest {
+        if let Some(&code) = table.get(&(prefix, k)) {
+            prefix = code;
+            continue;
+        }
+        out.write(prefix, width);
+        if next < 4096 {
+            table.insert((prefix, k), next);
+            // the decoder widens its codes once the table reaches the next power of two
+            if next == 1 << width && width < 12 {
+                width += 1;
+            }
+            next += 1;
+        } else {
+            out.write(clear, width);
+            table.clear();
+            width = min_code_size + 1;
+            next = end + 1;
+        }
+        prefix = k as u16;
+    }
+    out.write(prefix, width);
+    out.write(end, width);
+    out.finish()
+}
+
+// an animated GIF with an 8 level gray palette, palette index n means n robots or more
+struct GifWriter {
+    bytes: Vec<u8>,
+    width: u16,
+    height: u16,
+    // hundredths of a second per frame
+    delay: u16,
+}
+
+impl GifWriter {
+    const LEVELS: u8 = 8;
+
+    fn new(width: u16, height: u16, delay: u16) -> Self {
+        let mut bytes = b"GIF89a".to_vec();
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        // global color table of 2^(2 + 1) entries
+        bytes.extend([0xf2, 0, 0]);
+        for level in 0..Self::LEVELS {
+            let gray = 255 - level * (255 / (Self::LEVELS - 1));
+            bytes.extend([gray; 3]);
+        }
+        // loop forever
+        bytes.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+        GifWriter {
+            bytes,
+            width,
+            height,
+            delay,
+        }
+    }
+
+    fn frame(&mut self, pixels: &[u8]) {
+        self.bytes.extend([0x21, 0xf9, 4, 0]);
+        self.bytes.extend(self.delay.to_le_bytes());
+        self.bytes.extend([0, 0]);
+        self.bytes.push(0x2c);
+        self.bytes.extend([0, 0, 0, 0]);
+        self.bytes.extend(self.width.to_le_bytes());
+        self.bytes.extend(self.height.to_le_bytes());
+        self.bytes.push(0);
+        self.bytes.push(3);
+        for block in lzw_encode(pixels, 3).chunks(255) {
+            self.bytes.push(block.len() as u8);
+            self.bytes.extend(block);
+        }
+        self.bytes.push(0);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.bytes.push(0x3b);
+        self.bytes
+    }
+}
+
+fn gif_pixels(field: &RobotField, t: u64) -> Vec<u8> {
+    density(field, t)
+        .into_iter()
+        .flatten()
+        .map(|n| n.min(GifWriter::LEVELS as u32 - 1) as u8)
+        .collect()
+}
+
+fn to_gif(field: &RobotField, seconds: Range<u64>, delay: u16) -> Vec<u8> {
+    let mut gif = GifWriter::new(field.bound.0 as u16, field.bound.1 as u16, delay);
+    for t in seconds {
+        gif.frame(&gif_pixels(field, t));
+    }
+    gif.finish()
+}
+
+// a .gif path gets one animation, anything else is a directory of numbered pbm or pgm frames
+fn export_frames(
+    field: &RobotField,
+    seconds: Range<u64>,
+    out: &Path,
+    format: &str,
+) -> Result<usize> {
+    if out.extension().is_some_and(|e| e == "gif") {
+        std::fs::write(out, to_gif(field, seconds.clone(), 10))?;
+        return Ok(seconds.count());
+    }
+    let render = match format {
+        "pbm" => to_pbm,
+        "pgm" => to_pgm,
+        _ => return err!("unknown frame format: {format:?}"),
+    };
+    std::fs::create_dir_all(out)?;
+    let digits = seconds.end.max(1).ilog10() as usize + 1;
+    for t in seconds.clone() {
+        std::fs::write(
+            out.join(format!("frame_{t:0digits$}.{format}")),
+            render(field, t),
+        )?;
+    }
+    Ok(seconds.count())
+}
+
+// `--frames <from>..<to> --out <dir or file.gif>` writes the seconds in between as images,
+// `--format pbm` switches numbered frames from the default pgm
+fn export_from_args(robots: &[(Coord, Coord)], bound: Coord, args: &[String]) -> Result<()> {
+    let value = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    let Some(frames) = value("--frames") else {
+        return Ok(());
+    };
+    let Some((from, to)) = frames.split_once("..") else {
+        return err!("--frames expects <from>..<to>: {frames:?}");
+    };
+    let Some(out) = value("--out") else {
+        return err!("--frames needs an --out path");
+    };
+    let format = value("--format").map_or("pgm", |f| f.as_str());
+    let field = RobotField::new(robots, bound);
+    let count = export_frames(&field, from.parse()?..to.parse()?, Path::new(out), format)?;
+    println!("wrote {count} frames to {out}");
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
     let robots = parse_input(input)?;
-    part1(&robots)?;
-    part2(&robots)?;
+    let args: Vec<_> = std::env::args().collect();
+    let bound = bound_from_args(&args)?;
+    part1(&robots, bound)?;
+    part1_stepping(&robots, bound)?;
+    part2(&robots, bound)?;
+    export_from_args(&robots, bound, &args)?;
     Ok(())
 }
 
@@ -161,7 +568,34 @@ p=2,4 v=2,-3
 p=9,5 v=-3,-3";
     let robots = parse_input(input)?;
     assert_eq!(move_robots(&robots, 100, (11, 7)), 12);
-    assert_eq!(1, 1);
+    assert_eq!(part1(&robots, (11, 7))?, 12);
+    assert_eq!(part1_stepping(&robots, (11, 7))?, 12);
+
+    let field = RobotField::new(&robots, (11, 7));
+    let cycles = field.cycle_lengths();
+    assert_eq!(&cycles[..3], &[77, 77, 77]);
+    assert!(cycles.iter().all(|c| 77 % c == 0));
+    let big = 10u64.pow(18) + 5;
+    assert_eq!(field.positions_at(big), field.positions_at(big % 77));
+    assert_eq!(field.safety_factor(big), field.safety_factor(big % 77));
+    for t in [0, 1, 5, 100] {
+        let mut moved = robots.clone();
+        for _ in 0..t {
+            for robot in &mut moved {
+                moving(robot, (11, 7));
+            }
+        }
+        assert_eq!(field.robots_at(t), moved);
+    }
+    // a robot standing still or moving along a single axis has a shorter cycle
+    let field = RobotField::new(
+        &[((1, 1), (0, 0)), ((1, 1), (3, 0)), ((1, 1), (22, -14))],
+        (11, 7),
+    );
+    assert_eq!(field.cycle_lengths(), vec![1, 11, 1]);
+    let args = ["--bound", "11,7"].map(String::from);
+    assert_eq!(bound_from_args(&args)?, (11, 7));
+    assert_eq!(bound_from_args(&[])?, (101, 103));
     Ok(())
 }
 
@@ -169,8 +603,111 @@ p=9,5 v=-3,-3";
 fn real_input() -> Result<()> {
     let input = std::fs::read_to_string("input/input.txt").unwrap();
     let robots = parse_input(input)?;
-    assert_eq!(part1(&robots)?, 228410028);
-    assert_eq!(part2(&robots)?, 8258);
+    assert_eq!(part1(&robots, (101, 103))?, 228410028);
+    assert_eq!(part1_stepping(&robots, (101, 103))?, 228410028);
+    assert_eq!(part2(&robots, (101, 103))?, 8258);
+    assert_eq!(part2_min_distance(&robots, (101, 103))?, 8258);
     assert_eq!(2, 2);
     Ok(())
 }
+
+#[test]
+fn tree_detection() -> Result<()> {
+    // robots that all meet in a 5 by 5 block at second 500
+    let bound = (31, 37);
+    let robots: Vec<_> = (0..60i64)
+        .map(|i| {
+            let target = (10 + i % 5, 20 + i / 5 % 5);
+            let v = ((i * 7 + 3) % 31 - 15, (i * 11 + 5) % 37 - 18);
+            let p = (
+                (target.0 - v.0 * 500).rem_euclid(bound.0),
+                (target.1 - v.1 * 500).rem_euclid(bound.1),
+            );
+            (p, v)
+        })
+        .collect();
+    let tree = find_tree(&RobotField::new(&robots, bound))?;
+    assert_eq!(tree.second, 500);
+    assert!(tree.confidence > 3.0, "{}", tree.confidence);
+    let rows: Vec<_> = tree.frame.lines().collect();
+    assert_eq!(rows.len(), 37);
+    assert_eq!(&rows[20][8..17], "..*****..");
+
+    assert_eq!(crt((2, 3), (3, 5))?, 8);
+    assert_eq!(crt((1, 4), (3, 6))?, 9);
+    assert!(crt((0, 4), (1, 6)).is_err());
+    Ok(())
+}
+
+#[cfg(test)]
+fn lzw_decode(bytes: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1usize << min_code_size;
+    let mut width = min_code_size + 1;
+    let mut table: Vec<Vec<u8>> = vec![];
+    let reset = |table: &mut Vec<Vec<u8>>| {
+        *table = (0..clear + 2).map(|i| vec![i as u8]).collect();
+    };
+    reset(&mut table);
+    let (mut acc, mut bits, mut pos) = (0u32, 0, 0);
+    let mut prev: Option<Vec<u8>> = None;
+    let mut out = vec![];
+    loop {
+        while bits < width {
+            acc |= (bytes[pos] as u32) << bits;
+            pos += 1;
+            bits += 8;
+        }
+        let code = (acc & ((1 << width) - 1)) as usize;
+        acc >>= width;
+        bits -= width;
+        if code == clear {
+            reset(&mut table);
+            width = min_code_size + 1;
+            prev = None;
+            continue;
+        }
+        if code == clear + 1 {
+            return out;
+        }
+        let entry = match (&prev, table.get(code)) {
+            (_, Some(entry)) => entry.clone(),
+            (Some(p), None) => [p.clone(), vec![p[0]]].concat(),
+            (None, None) => panic!("bad code {code}"),
+        };
+        if let Some(p) = prev
+            && table.len() < 4096
+        {
+            table.push([p, vec![entry[0]]].concat());
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+        }
+        out.extend(&entry);
+        prev = Some(entry);
+    }
+}
+
+#[test]
+fn frame_export() -> Result<()> {
+    let robots = parse_input("p=0,0 v=1,0\np=0,0 v=1,0\np=2,1 v=0,1")?;
+    let field = RobotField::new(&robots, (3, 2));
+    assert_eq!(to_pbm(&field, 0), "P1\n# second 0\n3 2\n1 0 0\n0 0 1\n");
+    assert_eq!(to_pgm(&field, 1), "P2\n# second 1\n3 2\n2\n2 0 1\n2 2 2\n");
+    assert_eq!(gif_pixels(&field, 1), vec![0, 2, 1, 0, 0, 0]);
+
+    // long enough to fill the table and force clear codes
+    let pixels: Vec<u8> = (0..50000u32).map(|i| (i * i / 7 % 8) as u8).collect();
+    assert_eq!(lzw_decode(&lzw_encode(&pixels, 3), 3), pixels);
+    assert_eq!(lzw_decode(&lzw_encode(&[], 3), 3), vec![]);
+
+    let gif = to_gif(&field, 0..3, 10);
+    assert_eq!(&gif[..6], b"GIF89a");
+    assert_eq!(gif.last(), Some(&0x3b));
+
+    let dir = std::env::temp_dir().join(format!("aoc14-frames-{}", std::process::id()));
+    assert_eq!(export_frames(&field, 8..12, &dir, "pbm")?, 4);
+    assert!(dir.join("frame_08.pbm").exists() && dir.join("frame_11.pbm").exists());
+    assert!(export_frames(&field, 0..1, &dir, "png").is_err());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
-use std::io::{self, Read};
+use std::io::{self, BufRead, Read, Write};
 use std::time::Instant;
 
 #[allow(unused_macros)]
@@ -12,7 +12,7 @@ type Result<T> = ::std::result::Result<T, Box<dyn Error>>;
 
 type Coord = (isize, isize);
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Move {
     Up,
     Down,
@@ -22,11 +22,19 @@ enum Move {
 
 fn parse_input<T: AsRef<str>>(input: T) -> Result<(Vec<Vec<char>>, Vec<Move>)> {
     if let Some((map, moves)) = input.as_ref().trim().split_once("\n\n") {
-        let map = map
+        let map: Vec<Vec<char>> = map
             .trim()
             .lines()
             .map(|l| l.trim().chars().collect())
             .collect();
+        // a letter names one box, two boxes sharing it would merge once they touch
+        let mut letters = HashSet::new();
+        for b in find_boxes(&map) {
+            let (c, glyph) = b[0];
+            if glyph.is_ascii_uppercase() && glyph != 'O' && !letters.insert(glyph) {
+                return err!("box {glyph:?} at {c:?} reuses the letter of another box");
+            }
+        }
         let moves = moves
             .trim()
             .chars()
@@ -50,6 +58,24 @@ impl Move {
         }
     }
 
+    fn symbol(&self) -> char {
+        match self {
+            Move::Right => '>',
+            Move::Left => '<',
+            Move::Up => '^',
+            Move::Down => 'v',
+        }
+    }
+
+    fn reverse(self) -> Move {
+        match self {
+            Move::Right => Move::Left,
+            Move::Left => Move::Right,
+            Move::Up => Move::Down,
+            Move::Down => Move::Up,
+        }
+    }
+
     fn next_coord(&self, coord: Coord) -> Coord {
         let (x, y) = coord;
         match self {
@@ -235,16 +261,89 @@ fn find_robot(map: &[Vec<char>]) -> Coord {
     unreachable!("there is no robot in map")
 }
 
-fn sum_of_gps(map: &[Vec<char>]) -> usize {
-    let mut s = 0;
+// the cells of the box covering coord: 'O' is a single cell, '[' '-'... ']' runs along a row
+// and any other capital letter spreads over its 4-connected cells of the same letter,
+// parse_input makes sure each letter is a single box
+fn footprint(map: &[Vec<char>], coord: Coord) -> Vec<Coord> {
+    let cell = |c: Coord| {
+        usize::try_from(c.0)
+            .ok()
+            .zip(usize::try_from(c.1).ok())
+            .and_then(|(x, y)| map.get(x)?.get(y).cloned())
+            .unwrap_or('#')
+    };
+    match cell(coord) {
+        'O' => vec![coord],
+        '[' | '-' | ']' => {
+            let (x, mut y) = coord;
+            while cell((x, y)) != '[' && "[-".contains(cell((x, y - 1))) {
+                y -= 1;
+            }
+            let mut cells = vec![(x, y)];
+            while cell((x, y)) != ']' && "-]".contains(cell((x, y + 1))) {
+                y += 1;
+                cells.push((x, y));
+            }
+            cells
+        }
+        c if c.is_ascii_uppercase() => {
+            let mut cells = vec![coord];
+            let mut seen = HashSet::from([coord]);
+            let mut i = 0;
+            while i < cells.len() {
+                let (x, y) = cells[i];
+                for n in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if cell(n) == c && seen.insert(n) {
+                        cells.push(n);
+                    }
+                }
+                i += 1;
+            }
+            cells.sort();
+            cells
+        }
+        _ => vec![],
+    }
+}
+
+fn find_boxes(map: &[Vec<char>]) -> Vec<Vec<(Coord, char)>> {
+    let mut seen: HashSet<Coord> = HashSet::new();
+    let mut boxes = vec![];
     for (i, row) in map.iter().enumerate() {
-        for (j, c) in row.iter().enumerate() {
-            if c == &'O' || c == &'[' {
-                s += 100 * i + j;
+        for j in 0..row.len() {
+            let c = (i as isize, j as isize);
+            if seen.contains(&c) {
+                continue;
+            }
+            let cells = footprint(map, c);
+            if !cells.is_empty() {
+                seen.extend(&cells);
+                boxes.push(
+                    cells
+                        .iter()
+                        .map(|&(x, y)| ((x, y), map[x as usize][y as usize]))
+                        .collect(),
+                );
             }
         }
     }
-    s
+    boxes
+}
+
+// a box is measured from the top left corner of its bounding box
+fn box_gps(cells: &[(Coord, char)]) -> usize {
+    let top = cells.iter().map(|(c, _)| c.0).min().unwrap_or(0);
+    let left = cells.iter().map(|(c, _)| c.1).min().unwrap_or(0);
+    (100 * top + left) as usize
+}
+
+fn sum_of_gps(map: &[Vec<char>]) -> usize {
+    find_boxes(map).iter().map(|b| box_gps(b)).sum()
+}
+
+// part1, part2 and part2_box_as_robot only know about 'O' boxes
+fn only_single_boxes(map: &[Vec<char>]) -> bool {
+    map.iter().flatten().all(|c| "#.@O".contains(*c))
 }
 
 fn part1(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
@@ -267,30 +366,30 @@ fn part1(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
     Ok(result)
 }
 
-fn expand_map(map: &[Vec<char>]) -> Vec<Vec<char>> {
-    let mut new_map = vec![vec!['.'; map[0].len() * 2]; map.len()];
-
-    for (i, row) in map.iter().enumerate() {
-        for (j, &c) in row.iter().enumerate() {
-            if c == '#' {
-                new_map[i][j * 2] = '#';
-                new_map[i][j * 2 + 1] = '#';
-            } else if c == 'O' {
-                new_map[i][j * 2] = '[';
-                new_map[i][j * 2 + 1] = ']';
-            } else if c == '@' {
-                new_map[i][j * 2] = '@';
-            }
-        }
-    }
-
-    new_map
+// every cell becomes `factor` cells wide, 'O' boxes turn into '[' '-'... ']'
+fn expand_map(map: &[Vec<char>], factor: usize) -> Vec<Vec<char>> {
+    map.iter()
+        .map(|row| {
+            row.iter()
+                .flat_map(|&c| {
+                    (0..factor).map(move |k| match c {
+                        'O' if factor == 1 => 'O',
+                        'O' if k == 0 => '[',
+                        'O' if k + 1 == factor => ']',
+                        'O' => '-',
+                        '@' if k > 0 => '.',
+                        c => c,
+                    })
+                })
+                .collect()
+        })
+        .collect()
 }
 
 fn part2(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
     let _start = Instant::now();
 
-    let mut map = expand_map(map);
+    let mut map = expand_map(map, 2);
     let mut robot = find_robot(&map);
 
     // display_map(&map);
@@ -310,7 +409,7 @@ fn part2(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
 fn part2_box_as_robot(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
     let _start = Instant::now();
 
-    let mut map = expand_map(map);
+    let mut map = expand_map(map, 2);
     let mut robot = find_robot(&map);
 
     // display_map(&map);
@@ -331,14 +430,354 @@ fn part2_box_as_robot(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
     Ok(result)
 }
 
+// boxes as entities with their own footprint, walls and free space stay in the grid
+struct Warehouse {
+    grid: Vec<Vec<char>>,
+    boxes: Vec<Vec<(Coord, char)>>,
+    // which box covers a cell
+    owner: HashMap<Coord, usize>,
+    robot: Coord,
+}
+
+impl Warehouse {
+    fn new(map: &[Vec<char>]) -> Self {
+        let boxes = find_boxes(map);
+        let robot = find_robot(map);
+        let mut grid = map.to_vec();
+        for &(c, _) in boxes.iter().flatten() {
+            grid[c.0 as usize][c.1 as usize] = '.';
+        }
+        grid[robot.0 as usize][robot.1 as usize] = '.';
+        let mut warehouse = Warehouse {
+            grid,
+            boxes,
+            owner: HashMap::new(),
+            robot,
+        };
+        for id in 0..warehouse.boxes.len() {
+            warehouse.claim(id);
+        }
+        warehouse
+    }
+
+    fn claim(&mut self, id: usize) {
+        for &(c, _) in &self.boxes[id] {
+            self.owner.insert(c, id);
+        }
+    }
+
+    fn is_wall(&self, (x, y): Coord) -> bool {
+        usize::try_from(x)
+            .ok()
+            .zip(usize::try_from(y).ok())
+            .and_then(|(x, y)| self.grid.get(x)?.get(y))
+            .is_none_or(|&c| c == '#')
+    }
+
+    // the boxes that move together when the robot steps, None when one would hit a wall
+    fn push_set(&self, m: Move) -> Option<Vec<usize>> {
+        let mut pushed = vec![];
+        let mut queue = VecDeque::from([m.next_coord(self.robot)]);
+        while let Some(c) = queue.pop_front() {
+            if self.is_wall(c) {
+                return None;
+            }
+            let Some(&id) = self.owner.get(&c) else {
+                continue;
+            };
+            if pushed.contains(&id) {
+                continue;
+            }
+            pushed.push(id);
+            queue.extend(self.boxes[id].iter().map(|&(b, _)| m.next_coord(b)));
+        }
+        Some(pushed)
+    }
+
+    // moves the boxes one cell, the robot stays where it is
+    fn shift(&mut self, ids: &[usize], m: Move) {
+        for &id in ids {
+            for &(c, _) in &self.boxes[id] {
+                self.owner.remove(&c);
+            }
+        }
+        for &id in ids {
+            for cell in &mut self.boxes[id] {
+                cell.0 = m.next_coord(cell.0);
+            }
+            self.claim(id);
+        }
+    }
+
+    // the boxes pushed along, None when the move is blocked
+    fn step(&mut self, m: Move) -> Option<Vec<usize>> {
+        let pushed = self.push_set(m)?;
+        self.shift(&pushed, m);
+        self.robot = m.next_coord(self.robot);
+        Some(pushed)
+    }
+
+    fn sum_of_gps(&self) -> usize {
+        self.boxes.iter().map(|b| box_gps(b)).sum()
+    }
+
+    fn render(&self) -> Vec<Vec<char>> {
+        let mut map = self.grid.clone();
+        for &(c, glyph) in self.boxes.iter().flatten() {
+            map[c.0 as usize][c.1 as usize] = glyph;
+        }
+        map[self.robot.0 as usize][self.robot.1 as usize] = '@';
+        map
+    }
+}
+
+// one move as a diff: where the robot started and the boxes that moved along,
+// a blocked move keeps the robot in place and pushes nothing
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    m: Move,
+    robot: Coord,
+    moved: bool,
+    pushed: Vec<usize>,
+}
+
+struct Simulation {
+    warehouse: Warehouse,
+    moves: Vec<Move>,
+    // applied steps, the next move is moves[history.len()]
+    history: Vec<Step>,
+}
+
+impl Simulation {
+    fn new(map: Vec<Vec<char>>, moves: Vec<Move>) -> Self {
+        Simulation {
+            warehouse: Warehouse::new(&map),
+            moves,
+            history: vec![],
+        }
+    }
+
+    fn position(&self) -> usize {
+        self.history.len()
+    }
+
+    fn apply(&mut self, m: Move) -> Step {
+        let robot = self.warehouse.robot;
+        let pushed = self.warehouse.step(m);
+        let step = Step {
+            m,
+            robot,
+            moved: pushed.is_some(),
+            pushed: pushed.unwrap_or_default(),
+        };
+        self.history.push(step.clone());
+        step
+    }
+
+    fn forward(&mut self) -> Option<Step> {
+        let &m = self.moves.get(self.position())?;
+        Some(self.apply(m))
+    }
+
+    fn back(&mut self) -> Option<Step> {
+        let step = self.history.pop()?;
+        if step.moved {
+            self.warehouse.shift(&step.pushed, step.m.reverse());
+            self.warehouse.robot = step.robot;
+        }
+        Some(step)
+    }
+
+    // a live move drops whatever was ahead in the move list
+    fn push_move(&mut self, m: Move) -> Step {
+        self.moves.truncate(self.position());
+        self.moves.push(m);
+        self.apply(m)
+    }
+
+    fn jump_to(&mut self, n: usize) {
+        let n = n.min(self.moves.len());
+        while self.position() > n {
+            self.back();
+        }
+        while self.position() < n {
+            self.forward();
+        }
+    }
+
+    fn map(&self) -> Vec<Vec<char>> {
+        self.warehouse.render()
+    }
+
+    fn render(&self) -> String {
+        self.map()
+            .iter()
+            .map(|row| row.iter().collect::<String>() + "\n")
+            .collect()
+    }
+}
+
+fn save_log(moves: &[Move]) -> String {
+    let symbols: Vec<char> = moves.iter().map(|m| m.symbol()).collect();
+    symbols
+        .chunks(70)
+        .map(|line| line.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+fn load_log<T: AsRef<str>>(log: T) -> Result<Vec<Move>> {
+    log.as_ref()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(Move::new)
+        .collect()
+}
+
+// ^ v < > move the robot, b steps back, f steps forward, j<n> jumps to step n, q quits;
+// input is line buffered, not raw keypresses: keys take effect once enter is pressed
+// and the map is redrawn after every line
+fn interactive<R: BufRead, W: Write>(sim: &mut Simulation, keys: R, mut out: W) -> Result<()> {
+    write!(out, "{}", sim.render())?;
+    for line in keys.lines() {
+        let line = line?;
+        // typos are reported below the redrawn map instead of ending the session
+        let mut errors = vec![];
+        if let Some(n) = line.trim().strip_prefix('j') {
+            match n.trim().parse() {
+                Ok(n) => sim.jump_to(n),
+                Err(_) => errors.push(format!("{:?} is not a step number", n.trim())),
+            }
+        } else {
+            for key in line.chars() {
+                match key {
+                    'q' => return Ok(()),
+                    'b' => _ = sim.back(),
+                    'f' => _ = sim.forward(),
+                    c if c.is_whitespace() => {}
+                    c => match Move::new(c) {
+                        Ok(m) => _ = sim.push_move(m),
+                        Err(e) => errors.push(e.to_string()),
+                    },
+                }
+            }
+        }
+        writeln!(
+            out,
+            "\x1b[2J\x1b[H{}step {}/{} gps {}",
+            sim.render(),
+            sim.position(),
+            sim.moves.len(),
+            sim.warehouse.sum_of_gps()
+        )?;
+        for e in errors {
+            writeln!(out, "{e}")?;
+        }
+    }
+    Ok(())
+}
+
+fn warehouse_gps(map: &[Vec<char>], moves: &[Move], factor: usize) -> usize {
+    let mut warehouse = Warehouse::new(&expand_map(map, factor));
+    for &m in moves {
+        warehouse.step(m);
+    }
+    warehouse.sum_of_gps()
+}
+
+fn part1_warehouse(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = warehouse_gps(map, moves, 1);
+    println!("part1 with box entities: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+fn part2_warehouse(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
+    let _start = Instant::now();
+
+    let result = warehouse_gps(map, moves, 2);
+    println!("part2 with box entities: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+fn part2_simulation(map: &[Vec<char>], moves: &[Move]) -> Result<usize> {
+    let _start = Instant::now();
+
+    let mut sim = Simulation::new(expand_map(map, 2), moves.to_vec());
+    sim.jump_to(moves.len());
+
+    let result = sim.warehouse.sum_of_gps();
+    println!("part2 with simulation: {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
+// `--replay <log>` takes the moves from a saved log, `--wide` or `--expand <k>` widens the map,
+// `--step <n>` shows the map after n moves, `--interactive` reads lines of keys from the
+// terminal, applied on enter, and `--save <log>` writes the moves made
+fn simulate_from_args(map: &[Vec<char>], moves: &[Move], args: &[String]) -> Result<()> {
+    let value = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+    };
+    let has = |flag: &str| args.iter().any(|a| a == flag);
+    if !["--replay", "--step", "--interactive"]
+        .iter()
+        .any(|f| has(f))
+    {
+        return Ok(());
+    }
+    let moves = match value("--replay") {
+        Some(path) => load_log(std::fs::read_to_string(path)?)?,
+        None => moves.to_vec(),
+    };
+    let factor = match value("--expand") {
+        Some(k) => k.parse()?,
+        None if has("--wide") => 2,
+        None => 1,
+    };
+    if factor == 0 {
+        return err!("--expand needs a factor of at least 1");
+    }
+    let map = expand_map(map, factor);
+    let mut sim = Simulation::new(map, moves);
+    if let Some(n) = value("--step") {
+        sim.jump_to(n.parse()?);
+        print!("{}", sim.render());
+        println!(
+            "step {}/{} gps {}",
+            sim.position(),
+            sim.moves.len(),
+            sim.warehouse.sum_of_gps()
+        );
+    }
+    if has("--interactive") {
+        let tty = io::BufReader::new(std::fs::File::open("/dev/tty")?);
+        interactive(&mut sim, tty, io::stdout())?;
+    }
+    if let Some(path) = value("--save") {
+        std::fs::write(path, save_log(&sim.moves[..sim.position()]))?;
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
     let (map, moves) = parse_input(input)?;
-    part1(&map, &moves)?;
-    part2(&map, &moves)?;
-    part2_box_as_robot(&map, &moves)?;
+    if only_single_boxes(&map) {
+        part1(&map, &moves)?;
+        part2(&map, &moves)?;
+        part2_box_as_robot(&map, &moves)?;
+    }
+    part2_simulation(&map, &moves)?;
+    part1_warehouse(&map, &moves)?;
+    part2_warehouse(&map, &moves)?;
+    simulate_from_args(&map, &moves, &std::env::args().collect::<Vec<_>>())?;
     Ok(())
 }
 
@@ -356,8 +795,11 @@ fn example_input1() -> Result<()> {
 <^^>>>vv<v>>v<<";
     let (map, moves) = parse_input(input)?;
     assert_eq!(part1(&map, &moves)?, 2028);
+    assert_eq!(part1_warehouse(&map, &moves)?, 2028);
     assert_eq!(part2(&map, &moves)?, 1751);
     assert_eq!(part2_box_as_robot(&map, &moves)?, 1751);
+    assert_eq!(part2_simulation(&map, &moves)?, 1751);
+    assert_eq!(part2_warehouse(&map, &moves)?, 1751);
     Ok(())
 }
 
@@ -375,6 +817,8 @@ fn example_input2() -> Result<()> {
     let (map, moves) = parse_input(input)?;
     assert_eq!(part2(&map, &moves)?, 618);
     assert_eq!(part2_box_as_robot(&map, &moves)?, 618);
+    assert_eq!(part2_simulation(&map, &moves)?, 618);
+    assert_eq!(part2_warehouse(&map, &moves)?, 618);
     Ok(())
 }
 
@@ -403,8 +847,11 @@ vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
 v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
     let (map, moves) = parse_input(input)?;
     assert_eq!(part1(&map, &moves)?, 10092);
+    assert_eq!(part1_warehouse(&map, &moves)?, 10092);
     assert_eq!(part2(&map, &moves)?, 9021);
     assert_eq!(part2_box_as_robot(&map, &moves)?, 9021);
+    assert_eq!(part2_simulation(&map, &moves)?, 9021);
+    assert_eq!(part2_warehouse(&map, &moves)?, 9021);
     Ok(())
 }
 #[test]
@@ -412,7 +859,147 @@ fn real_input() -> Result<()> {
     let input = std::fs::read_to_string("input/input.txt").unwrap();
     let (map, moves) = parse_input(input)?;
     assert_eq!(part1(&map, &moves)?, 1538871);
+    assert_eq!(part1_warehouse(&map, &moves)?, 1538871);
     assert_eq!(part2(&map, &moves)?, 1543338);
     assert_eq!(part2_box_as_robot(&map, &moves)?, 1543338);
+    assert_eq!(part2_simulation(&map, &moves)?, 1543338);
+    assert_eq!(part2_warehouse(&map, &moves)?, 1543338);
+    Ok(())
+}
+
+#[test]
+fn replayable_simulation() -> Result<()> {
+    let input = "#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^";
+    let (map, moves) = parse_input(input)?;
+    let mut sim = Simulation::new(expand_map(&map, 2), moves.clone());
+    let start = sim.render();
+    let step = sim.forward().unwrap();
+    assert_eq!(step.robot, (3, 10));
+    let pushed: Vec<_> = step
+        .pushed
+        .iter()
+        .flat_map(|&id| sim.warehouse.boxes[id].clone())
+        .collect();
+    assert_eq!(
+        pushed,
+        vec![((3, 7), '['), ((3, 8), ']'), ((3, 5), '['), ((3, 6), ']')]
+    );
+    sim.jump_to(moves.len());
+    let end = sim.render();
+    assert_eq!(sim.warehouse.sum_of_gps(), 618);
+    sim.jump_to(0);
+    assert_eq!(sim.render(), start);
+
+    // a live move replaces the rest of the move list
+    sim.jump_to(3);
+    let before = sim.render();
+    let step = sim.push_move(Move::Right);
+    assert!(step.moved);
+    assert_eq!(sim.moves.len(), 4);
+    sim.back();
+    assert_eq!(sim.render(), before);
+
+    let log = save_log(&moves);
+    assert_eq!(log, "<vv<<^^<<^^\n");
+    let mut replay = Simulation::new(expand_map(&map, 2), load_log(&log)?);
+    replay.jump_to(usize::MAX);
+    assert_eq!(replay.render(), end);
+    assert!(load_log("<x>").is_err());
+
+    // a blocked move is still a step
+    let mut sim = Simulation::new(map.clone(), vec![]);
+    let before = sim.render();
+    let step = sim.push_move(Move::Right);
+    assert!(!step.moved && step.pushed.is_empty());
+    assert_eq!((sim.position(), sim.render()), (1, before));
+
+    let mut sim = Simulation::new(map.clone(), vec![]);
+    let mut out = vec![];
+    interactive(&mut sim, "<<\nb\nj0\n^q\nv".as_bytes(), &mut out)?;
+    assert_eq!(sim.moves, vec![Move::Up]);
+    assert_eq!(sim.position(), 1);
+    assert!(String::from_utf8(out)?.contains("step 0/2"));
+
+    let mut sim = Simulation::new(map.clone(), vec![]);
+    let mut out = vec![];
+    interactive(&mut sim, "<x<\nj2a\nv".as_bytes(), &mut out)?;
+    assert_eq!(sim.moves, vec![Move::Left, Move::Left, Move::Down]);
+    let out = String::from_utf8(out)?;
+    assert!(out.contains("'x' is not a valid move"));
+    assert!(out.contains("\"2a\" is not a step number"));
+    Ok(())
+}
+
+#[test]
+fn box_footprints() -> Result<()> {
+    let input = "########
+#......#
+#.AA.O.#
+#..A@..#
+#......#
+########
+
+<^<v<";
+    let (map, moves) = parse_input(input)?;
+    assert_eq!(footprint(&map, (3, 3)), vec![(2, 2), (2, 3), (3, 3)]);
+    assert_eq!(expand_map(&map, 3)[2][15..18], ['[', '-', ']']);
+    let wide = expand_map(&map, 3);
+    assert_eq!(footprint(&wide, (2, 16)), vec![(2, 15), (2, 16), (2, 17)]);
+    assert_eq!(footprint(&wide, (2, 6)).len(), 9);
+
+    // the L moves left once, then hits the wall with its top row
+    let mut warehouse = Warehouse::new(&map);
+    let steps: Vec<_> = moves.iter().map(|&m| warehouse.step(m).is_some()).collect();
+    assert_eq!(steps, vec![true, true, false, true, false]);
+    let rendered: Vec<String> = warehouse
+        .render()
+        .iter()
+        .map(|r| r.iter().collect())
+        .collect();
+    assert_eq!(rendered[2], "#AA..O.#");
+    assert_eq!(rendered[3], "#.A@...#");
+    assert_eq!(warehouse.sum_of_gps(), 201 + 205);
+    assert_eq!(warehouse.sum_of_gps(), sum_of_gps(&warehouse.render()));
+
+    // letter boxes stay with the footprint based solvers
+    let (blocked, up) = parse_input("#####\n#.A.#\n#.O.#\n#.@.#\n#####\n\n^")?;
+    assert!(!only_single_boxes(&blocked));
+    assert!(only_single_boxes(&parse_input("#.O@#\n\n<")?.0));
+    assert_eq!(part1_warehouse(&blocked, &up)?, 102 + 202);
+    assert_eq!(part2_warehouse(&blocked, &up)?, 104 + 204);
+    assert_eq!(part2_simulation(&blocked, &up)?, 104 + 204);
+
+    let args = |a: &[&str]| a.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+    assert!(simulate_from_args(&map, &moves, &args(&["--step", "2", "--expand", "0"])).is_err());
+    assert!(simulate_from_args(&map, &moves, &args(&["--step", "2", "--expand", "x"])).is_err());
+    simulate_from_args(&map, &moves, &args(&["--step", "2", "--expand", "3"]))?;
+
+    // a box keeps its identity when it runs into another one
+    let input = "########\n#......#\n#A.B@..#\n#......#\n########\n\n<v<^";
+    let (collide, collide_moves) = parse_input(input)?;
+    let mut sim = Simulation::new(collide, collide_moves.clone());
+    sim.jump_to(collide_moves.len());
+    assert_eq!(sim.render().lines().nth(1), Some("#.B....#"));
+    assert_eq!(sim.warehouse.sum_of_gps(), 201 + 102);
+    assert_eq!(sum_of_gps(&sim.map()), 201 + 102);
+    assert!(parse_input(input.replace('B', "A")).is_err());
+
+    // the grid scan and the entities agree on every expansion, and stepping back undoes it all
+    for factor in 1..=4 {
+        let mut sim = Simulation::new(expand_map(&map, factor), moves.clone());
+        let start = sim.render();
+        sim.jump_to(moves.len());
+        assert_eq!(sim.warehouse.sum_of_gps(), sum_of_gps(&sim.map()));
+        sim.jump_to(0);
+        assert_eq!(sim.render(), start);
+    }
     Ok(())
 }
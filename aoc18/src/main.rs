@@ -1,4 +1,4 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::io::{self, Read};
 use std::time::Instant;
@@ -63,6 +63,82 @@ fn part1(bytes: &[Coord], count: usize, bound: Coord) -> Result<usize> {
     Ok(result)
 }
 
+fn falling_path(bytes: &[Coord], fallen: usize, bound: Coord, wait: bool) -> Option<Vec<Coord>> {
+    // the first `fallen` bytes are down at time 0, then one more byte lands each step;
+    // a cell is only off limits from the step its byte lands on
+    let start = (0, 0);
+    let mut drop_time = HashMap::new();
+    for (i, &byte) in bytes.iter().enumerate() {
+        drop_time
+            .entry(byte)
+            .or_insert((i + 1).saturating_sub(fallen));
+    }
+    let settle = bytes.len().saturating_sub(fallen);
+    let free = |c: Coord, t: usize| drop_time.get(&c).is_none_or(|&d| t < d);
+    if !free(start, 0) {
+        return None;
+    }
+
+    let moves: &[Coord] = if wait {
+        &[(-1, 0), (1, 0), (0, -1), (0, 1), (0, 0)]
+    } else {
+        &[(-1, 0), (1, 0), (0, -1), (0, 1)]
+    };
+    let mut parents: Vec<HashMap<Coord, Coord>> = vec![HashMap::from([(start, start)])];
+    let mut seen = HashSet::from([start]);
+    let mut stale = 0;
+    while !parents.last().unwrap().contains_key(&bound) {
+        let t = parents.len();
+        let mut layer = HashMap::new();
+        let mut frontier: Vec<_> = parents[t - 1].keys().cloned().collect();
+        frontier.sort();
+        for cur in frontier {
+            for (dx, dy) in moves {
+                let (nx, ny) = (cur.0 + dx, cur.1 + dy);
+                if nx < 0 || ny < 0 || nx > bound.0 || ny > bound.1 || !free((nx, ny), t) {
+                    continue;
+                }
+                layer.entry((nx, ny)).or_insert(cur);
+            }
+        }
+        if layer.is_empty() {
+            return None;
+        }
+        let before = seen.len();
+        seen.extend(layer.keys().cloned());
+        // once every byte is down the grid is static, two layers without a new cell means stuck
+        stale = if t > settle && seen.len() == before {
+            stale + 1
+        } else {
+            0
+        };
+        if stale > 2 {
+            return None;
+        }
+        parents.push(layer);
+    }
+
+    let mut path = vec![bound];
+    for layer in parents.iter().skip(1).rev() {
+        path.push(layer[path.last().unwrap()]);
+    }
+    path.reverse();
+    Some(path)
+}
+
+fn part1_falling(bytes: &[Coord], fallen: usize, bound: Coord, wait: bool) -> Result<usize> {
+    let _start = Instant::now();
+
+    let Some(path) = falling_path(bytes, fallen, bound, wait) else {
+        return err!("exit is unreachable while bytes keep falling");
+    };
+    let result = path.len() - 1;
+
+    println!("part1 with falling bytes (wait: {wait}): {result}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
 fn reachable(
     cur: Coord,
     corrupted: &HashSet<Coord>,
@@ -139,15 +215,238 @@ fn part2_dfs_binary_search(bytes: &[Coord], count: usize, bound: Coord) -> Resul
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
+
+#[derive(Debug)]
+struct DisjointSet {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        DisjointSet {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        match self.rank[a].cmp(&self.rank[b]) {
+            std::cmp::Ordering::Less => self.parent[a] = b,
+            std::cmp::Ordering::Greater => self.parent[b] = a,
+            std::cmp::Ordering::Equal => {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+    }
+}
+
+// Start and exit split the outside of the grid into two arcs, each arc is a virtual node.
+// Corrupted cells are joined with their 8 neighbours and with the arc they touch, the
+// walker is cut off exactly when one group of corrupted cells touches both arcs.
+// An interior start or exit can be walled in by a ring that never reaches the border,
+// so then the last path found is kept and searched again only when a byte lands on it.
+#[derive(Debug)]
+struct Blockade {
+    bound: Coord,
+    start: Coord,
+    exit: Coord,
+    corrupted: Vec<bool>,
+    cells: DisjointSet,
+    // cells of a free path from start to exit, None when both are on the border
+    path: Option<Vec<bool>>,
+    blocked: bool,
+}
+
+impl Blockade {
+    fn new(bound: Coord, start: Coord, exit: Coord) -> Result<Self> {
+        let size = ((bound.0 + 1) * (bound.1 + 1)) as usize;
+        let mut blockade = Blockade {
+            bound,
+            start,
+            exit,
+            corrupted: vec![false; size],
+            cells: DisjointSet::new(size + 2),
+            path: None,
+            blocked: false,
+        };
+        for cell in [start, exit] {
+            if !blockade.contains(cell) {
+                return err!("{cell:?} is outside of the {bound:?} grid");
+            }
+        }
+        if [start, exit]
+            .iter()
+            .any(|&c| blockade.perimeter_index(c).is_none())
+        {
+            blockade.path = blockade.find_path();
+        }
+        Ok(blockade)
+    }
+
+    fn contains(&self, (x, y): Coord) -> bool {
+        x >= 0 && y >= 0 && x <= self.bound.0 && y <= self.bound.1
+    }
+
+    fn index(&self, (x, y): Coord) -> usize {
+        (y * (self.bound.0 + 1) + x) as usize
+    }
+
+    fn find_path(&self) -> Option<Vec<bool>> {
+        let mut from = vec![None; self.corrupted.len()];
+        from[self.index(self.start)] = Some(self.start);
+        let mut queue = VecDeque::from([self.start]);
+        while let Some(cur) = queue.pop_front() {
+            if cur == self.exit {
+                let mut path = vec![false; self.corrupted.len()];
+                let mut c = cur;
+                while c != self.start {
+                    path[self.index(c)] = true;
+                    c = from[self.index(c)].unwrap();
+                }
+                path[self.index(c)] = true;
+                return Some(path);
+            }
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let next = (cur.0 + dx, cur.1 + dy);
+                if self.contains(next) && !self.corrupted[self.index(next)] {
+                    let n = self.index(next);
+                    if from[n].is_none() {
+                        from[n] = Some(cur);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn perimeter_index(&self, (x, y): Coord) -> Option<isize> {
+        let (w, h) = self.bound;
+        if x < 0 || y < 0 || x > w || y > h {
+            None
+        } else if y == 0 {
+            Some(x)
+        } else if x == w {
+            Some(w + y)
+        } else if y == h {
+            Some(w + h + (w - x))
+        } else if x == 0 {
+            Some(2 * w + h + (h - y))
+        } else {
+            None
+        }
+    }
+
+    fn arc(&self, cell: Coord) -> Option<usize> {
+        let p = self.perimeter_index(cell)?;
+        let (s, e) = (
+            self.perimeter_index(self.start)?,
+            self.perimeter_index(self.exit)?,
+        );
+        let clockwise = if s <= e {
+            s < p && p < e
+        } else {
+            p > s || p < e
+        };
+        Some(self.corrupted.len() + clockwise as usize)
+    }
+
+    fn drop_byte(&mut self, byte: Coord) -> Result<bool> {
+        let (x, y) = byte;
+        if !self.contains(byte) {
+            return err!("byte {byte:?} falls outside of {:?}", self.bound);
+        }
+        let i = self.index(byte);
+        if self.blocked || self.corrupted[i] {
+            return Ok(self.blocked);
+        }
+        self.corrupted[i] = true;
+        if byte == self.start || byte == self.exit {
+            self.blocked = true;
+            return Ok(true);
+        }
+        if let Some(path) = &self.path {
+            if path[i] {
+                self.path = self.find_path();
+                self.blocked = self.path.is_none();
+            }
+            return Ok(self.blocked);
+        }
+        if let Some(arc) = self.arc(byte) {
+            self.cells.union(i, arc);
+        }
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx > self.bound.0 || ny > self.bound.1 {
+                    continue;
+                }
+                let n = self.index((nx, ny));
+                if self.corrupted[n] {
+                    self.cells.union(i, n);
+                }
+            }
+        }
+        let arcs = self.corrupted.len();
+        self.blocked =
+            self.start != self.exit && self.cells.find(arcs) == self.cells.find(arcs + 1);
+        Ok(self.blocked)
+    }
+}
+
+fn first_blocking_byte(
+    bytes: &[Coord],
+    bound: Coord,
+    start: Coord,
+    exit: Coord,
+) -> Result<Option<(usize, Coord)>> {
+    let mut blockade = Blockade::new(bound, start, exit)?;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if blockade.drop_byte(byte)? {
+            return Ok(Some((i, byte)));
+        }
+    }
+    Ok(None)
+}
+
+fn part2_union_find(bytes: &[Coord], bound: Coord) -> Result<Coord> {
+    let _start = Instant::now();
+
+    let Some((_, result)) = first_blocking_byte(bytes, bound, (0, 0), bound)? else {
+        return err!("exit is still reachable after all bytes fell");
+    };
+
+    println!("part2 with union find: {result:?}");
+    println!("> Time elapsed is: {:?}", _start.elapsed());
+    Ok(result)
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
     let bytes = parse_input(input)?;
     part1(&bytes, 1024, (70, 70))?;
+    part1_falling(&bytes, 1024, (70, 70), false)?;
+    part1_falling(&bytes, 1024, (70, 70), true)?;
     part2_dfs(&bytes, 1024, (70, 70))?;
     part2_bfs(&bytes, 1024, (70, 70))?;
     part2_dfs_binary_search(&bytes, 1024, (70, 70))?;
+    part2_union_find(&bytes, (70, 70))?;
     Ok(())
 }
 
@@ -183,6 +482,7 @@ fn example_input() -> Result<()> {
     assert_eq!(part2_dfs(&bytes, 12, (6, 6))?, (6, 1));
     assert_eq!(part2_bfs(&bytes, 12, (6, 6))?, (6, 1));
     assert_eq!(part2_dfs_binary_search(&bytes, 12, (6, 6))?, (6, 1));
+    assert_eq!(part2_union_find(&bytes, (6, 6))?, (6, 1));
     assert_eq!(1, 1);
     Ok(())
 }
@@ -195,6 +495,85 @@ fn real_input() -> Result<()> {
     assert_eq!(part2_dfs(&bytes, 1024, (70, 70))?, (31, 22));
     assert_eq!(part2_bfs(&bytes, 1024, (70, 70))?, (31, 22));
     assert_eq!(part2_dfs_binary_search(&bytes, 1024, (70, 70))?, (31, 22));
+    assert_eq!(part2_union_find(&bytes, (70, 70))?, (31, 22));
     assert_eq!(2, 2);
     Ok(())
 }
+
+#[test]
+fn blocking_byte_custom_cells() -> Result<()> {
+    fn connected(corrupted: &HashSet<Coord>, bound: Coord, start: Coord, exit: Coord) -> bool {
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(cur) = queue.pop_front() {
+            if cur == exit {
+                return true;
+            }
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let next = (cur.0 + dx, cur.1 + dy);
+                if next.0 >= 0
+                    && next.1 >= 0
+                    && next.0 <= bound.0
+                    && next.1 <= bound.1
+                    && !corrupted.contains(&next)
+                    && visited.insert(next)
+                {
+                    queue.push_back(next);
+                }
+            }
+        }
+        false
+    }
+
+    let bound = (9, 6);
+    let bytes: Vec<_> = (0..70)
+        .map(|i| ((i * 7 + 3) % 10, (i * 5 + i / 10) % 7))
+        .collect();
+    for (start, exit) in [
+        ((0, 0), (9, 6)),
+        ((0, 3), (9, 2)),
+        ((4, 0), (5, 6)),
+        ((9, 0), (0, 6)),
+        ((3, 3), (9, 6)),
+        ((0, 0), (6, 2)),
+        ((2, 2), (7, 4)),
+    ] {
+        let mut corrupted = HashSet::new();
+        let expected = bytes.iter().enumerate().find_map(|(i, &b)| {
+            corrupted.insert(b);
+            if corrupted.contains(&start) || corrupted.contains(&exit) {
+                return Some((i, b));
+            }
+            (!connected(&corrupted, bound, start, exit)).then_some((i, b))
+        });
+        assert!(expected.is_some());
+        assert_eq!(first_blocking_byte(&bytes, bound, start, exit)?, expected);
+    }
+    // four bytes wall in an interior start without touching the border
+    let ring = [(3, 2), (2, 3), (4, 3), (3, 4)];
+    assert_eq!(
+        first_blocking_byte(&ring, bound, (3, 3), (9, 6))?,
+        Some((3, (3, 4)))
+    );
+    assert_eq!(first_blocking_byte(&ring, bound, (3, 3), (3, 3))?, None);
+    assert!(Blockade::new(bound, (10, 3), (9, 6)).is_err());
+    Ok(())
+}
+
+#[test]
+fn falling_bytes_path() -> Result<()> {
+    let bound = (6, 6);
+    let bytes = parse_input("5,4\n4,2\n4,5\n3,0\n2,1\n6,3\n2,4\n1,5\n0,6\n3,3\n2,6\n5,1")?;
+    assert_eq!(part1_falling(&bytes, 12, bound, false)?, 22);
+
+    // a wall along x = 1 closes at time 4, one step too late for the walker heading down
+    let bound = (2, 3);
+    let bytes = [(1, 3), (1, 2), (1, 1), (1, 0)];
+    assert_eq!(
+        falling_path(&bytes, 0, bound, true),
+        Some(vec![(0, 0), (0, 1), (1, 1), (2, 1), (2, 2), (2, 3)])
+    );
+    assert_eq!(part1_falling(&bytes, 0, bound, false)?, 5);
+    assert!(falling_path(&bytes, 3, bound, true).is_none());
+    Ok(())
+}
@@ -1,8 +1,10 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::io::{self, Read};
 use std::time::Instant;
 
+use itertools::Itertools;
+
 #[allow(unused_macros)]
 macro_rules! err {
     ($($tt:tt)*) => { Err(Box::<dyn Error>::from(format!($($tt)*))) }
@@ -11,6 +13,7 @@ macro_rules! err {
 type Result<T> = ::std::result::Result<T, Box<dyn Error>>;
 
 type Gate = [String; 4];
+type Equations = HashMap<String, (String, String, String)>;
 
 fn parse_input<T: AsRef<str>>(input: T) -> Result<(HashMap<String, usize>, Vec<Gate>)> {
     if let Some((l, r)) = input.as_ref().trim().split_once("\n\n") {
@@ -40,177 +43,811 @@ fn parse_input<T: AsRef<str>>(input: T) -> Result<(HashMap<String, usize>, Vec<G
                 ]
             })
             .collect();
+        bus_width(wires.keys(), "x")?;
+        bus_width(wires.keys(), "y")?;
+        bus_width(gates.iter().map(|g| &g[3]), "z")?;
         return Ok((wires, gates));
     }
     err!("unable to parse input")
 }
 
-fn eval(o1: usize, op: &str, o2: usize) -> usize {
-    match op {
-        "AND" => o1 & o2,
-        "OR" => o1 | o2,
-        "XOR" => o1 ^ o2,
-        _ => unreachable!("unknow gate: {:?}", op),
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Op {
+    And,
+    Or,
+    Xor,
+}
+
+impl Op {
+    fn parse(op: &str) -> Result<Op> {
+        match op {
+            "AND" => Ok(Op::And),
+            "OR" => Ok(Op::Or),
+            "XOR" => Ok(Op::Xor),
+            _ => err!("unknow gate: {:?}", op),
+        }
     }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::And => "&",
+            Op::Or => "|",
+            Op::Xor => "^",
+        }
+    }
+
+    fn eval(self, o1: u8, o2: u8) -> u8 {
+        match self {
+            Op::And => o1 & o2,
+            Op::Or => o1 | o2,
+            Op::Xor => o1 ^ o2,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Circuit {
+    index: HashMap<String, usize>,
+    // gates in topological order: (o1, op, o2, rhs) as wire ids
+    order: Vec<(usize, Op, usize, usize)>,
+    x: Vec<usize>,
+    y: Vec<usize>,
+    z: Vec<usize>,
 }
 
-fn run_circuit(wires: &HashMap<String, usize>, gates: &[Gate]) -> usize {
-    let mut wires = wires.to_owned();
-    let mut queue = VecDeque::new();
-    queue.extend(gates.iter().cloned());
+impl Circuit {
+    fn compile(wires: &HashMap<String, usize>, gates: &[Gate]) -> Result<Circuit> {
+        fn intern(index: &mut HashMap<String, usize>, wire: &str) -> usize {
+            let id = index.len();
+            *index.entry(wire.to_string()).or_insert(id)
+        }
+
+        let mut index = HashMap::new();
+        let mut inputs: Vec<_> = wires.keys().collect();
+        inputs.sort();
+        for w in inputs {
+            intern(&mut index, w);
+        }
+        let mut compiled = Vec::with_capacity(gates.len());
+        for [o1, op, o2, rhs] in gates {
+            let (o1, o2, rhs) = (
+                intern(&mut index, o1),
+                intern(&mut index, o2),
+                intern(&mut index, rhs),
+            );
+            compiled.push((o1, Op::parse(op)?, o2, rhs));
+        }
+
+        let mut driver = vec![None; index.len()];
+        for (g, &(_, _, _, rhs)) in compiled.iter().enumerate() {
+            if driver[rhs].replace(g).is_some() || rhs < wires.len() {
+                return err!("wire {} is driven more than once", wire_name(&index, rhs));
+            }
+        }
+        let mut undriven: Vec<_> = compiled
+            .iter()
+            .flat_map(|&(o1, _, o2, _)| [o1, o2])
+            .filter(|&w| w >= wires.len() && driver[w].is_none())
+            .map(|w| wire_name(&index, w))
+            .collect();
+        if !undriven.is_empty() {
+            undriven.sort();
+            undriven.dedup();
+            return err!("undriven wires: {}", undriven.join(","));
+        }
+
+        let mut pending = vec![0; compiled.len()];
+        let mut consumers = vec![vec![]; index.len()];
+        for (g, &(o1, _, o2, _)) in compiled.iter().enumerate() {
+            for w in [o1, o2] {
+                if driver[w].is_some() {
+                    pending[g] += 1;
+                    consumers[w].push(g);
+                }
+            }
+        }
+        let mut queue: VecDeque<_> = (0..compiled.len()).filter(|&g| pending[g] == 0).collect();
+        let mut order = Vec::with_capacity(compiled.len());
+        while let Some(g) = queue.pop_front() {
+            order.push(compiled[g]);
+            for &c in &consumers[compiled[g].3] {
+                pending[c] -= 1;
+                if pending[c] == 0 {
+                    queue.push_back(c);
+                }
+            }
+        }
+        if order.len() < compiled.len() {
+            // every gate left over waits on another left over gate, so following drivers must loop
+            let mut g = (0..compiled.len()).find(|&g| pending[g] > 0).unwrap();
+            let mut seen = vec![false; compiled.len()];
+            while !seen[g] {
+                seen[g] = true;
+                let (o1, _, o2, _) = compiled[g];
+                g = [o1, o2]
+                    .into_iter()
+                    .filter_map(|w| driver[w])
+                    .find(|&d| pending[d] > 0)
+                    .unwrap();
+            }
+            let mut cycle = vec![wire_name(&index, compiled[g].3)];
+            let start = g;
+            loop {
+                let (o1, _, o2, _) = compiled[g];
+                g = [o1, o2]
+                    .into_iter()
+                    .filter_map(|w| driver[w])
+                    .find(|&d| pending[d] > 0)
+                    .unwrap();
+                if g == start {
+                    break;
+                }
+                cycle.push(wire_name(&index, compiled[g].3));
+            }
+            return err!("circuit has a cycle: {}", cycle.join(" <- "));
+        }
+
+        let bus = |prefix: &str| {
+            let mut bus: Vec<_> = index
+                .iter()
+                .filter_map(|(w, &id)| Some((bus_index(w, prefix)?, id)))
+                .collect();
+            bus.sort();
+            bus.into_iter().map(|(_, id)| id).collect::<Vec<_>>()
+        };
+        let (x, y, z) = (bus("x"), bus("y"), bus("z"));
+        Ok(Circuit {
+            index,
+            order,
+            x,
+            y,
+            z,
+        })
+    }
+
+    fn run(&self, values: &mut [u8]) -> Vec<u8> {
+        for &(o1, op, o2, rhs) in &self.order {
+            values[rhs] = op.eval(values[o1], values[o2]);
+        }
+        self.z.iter().map(|&w| values[w]).collect()
+    }
 
-    while let Some([o1, op, o2, rhs]) = queue.pop_front() {
-        if let Some(&o1) = wires.get(&o1) {
-            if let Some(&o2) = wires.get(&o2) {
-                wires.insert(rhs.to_string(), eval(o1, &op, o2));
-                continue;
+    fn evaluate(&self, wires: &HashMap<String, usize>) -> Vec<u8> {
+        let mut values = vec![0; self.index.len()];
+        for (w, &v) in wires {
+            if let Some(&id) = self.index.get(w) {
+                values[id] = v as u8;
             }
         }
-        queue.push_back([o1, op, o2, rhs]);
+        self.run(&mut values)
+    }
+
+    fn add_bits(&self, x: &[u8], y: &[u8]) -> Vec<u8> {
+        let mut values = vec![0; self.index.len()];
+        for (bus, bits) in [(&self.x, x), (&self.y, y)] {
+            for (&w, &b) in bus.iter().zip(bits) {
+                values[w] = b;
+            }
+        }
+        self.run(&mut values)
+    }
+
+    fn add(&self, x: u128, y: u128) -> Result<u128> {
+        let mut wires = HashMap::new();
+        encode_bus(&mut wires, "x", &encode(x, self.x.len()));
+        encode_bus(&mut wires, "y", &encode(y, self.y.len()));
+        decode(&self.evaluate(&wires))
+    }
+}
+
+fn bus_index(wire: &str, prefix: &str) -> Option<usize> {
+    let digits = wire.strip_prefix(prefix)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn bus_width<'a>(names: impl IntoIterator<Item = &'a String>, prefix: &str) -> Result<usize> {
+    let mut bits: Vec<_> = names
+        .into_iter()
+        .filter_map(|w| bus_index(w, prefix))
+        .collect();
+    bits.sort();
+    if let Some(i) = (0..bits.len()).find(|&i| bits[i] != i) {
+        return err!(
+            "bus {prefix} is not contiguous at bit {i}: {prefix}00..{prefix}{:02} expected",
+            bits.len() - 1
+        );
     }
+    Ok(bits.len())
+}
+
+fn encode(value: u128, width: usize) -> Vec<u8> {
+    (0..width)
+        .map(|i| if i < 128 { (value >> i & 1) as u8 } else { 0 })
+        .collect()
+}
 
-    dec(&wires, "z")
+fn decode(bits: &[u8]) -> Result<u128> {
+    if bits.iter().skip(128).any(|&b| b != 0) {
+        return err!("{} bit value does not fit in u128", bits.len());
+    }
+    Ok(bits
+        .iter()
+        .take(128)
+        .rev()
+        .fold(0, |s, &b| s << 1 | b as u128))
+}
+
+// sets prefix00, prefix01... from the bits, least significant first
+fn encode_bus(wires: &mut HashMap<String, usize>, prefix: &str, bits: &[u8]) {
+    for (i, &b) in bits.iter().enumerate() {
+        wires.insert(format!("{prefix}{i:02}"), b as usize);
+    }
+}
+
+fn decode_bus(wires: &HashMap<String, usize>, prefix: &str) -> Result<Vec<u8>> {
+    let width = bus_width(wires.keys(), prefix)?;
+    (0..width)
+        .map(|i| {
+            let wire = format!("{prefix}{i:02}");
+            match wires.get(&wire) {
+                Some(&v) => Ok(v as u8),
+                None => err!("missing wire {wire}"),
+            }
+        })
+        .collect()
 }
 
-fn part1(wires: &HashMap<String, usize>, gates: &[Gate]) -> Result<usize> {
+fn wire_name(index: &HashMap<String, usize>, id: usize) -> String {
+    index.iter().find(|&(_, &i)| i == id).unwrap().0.clone()
+}
+
+fn part1(wires: &HashMap<String, usize>, gates: &[Gate]) -> Result<u128> {
     let _start = Instant::now();
 
-    let result = run_circuit(wires, gates);
+    let result = decode(&Circuit::compile(wires, gates)?.evaluate(wires))?;
 
     println!("part1: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
 
-fn dec(wires: &HashMap<String, usize>, prefix: &str) -> usize {
-    let mut num: Vec<_> = wires
-        .iter()
-        .filter(|(k, _)| k.starts_with(prefix))
-        .collect();
-    num.sort();
-    num.iter()
-        .rev()
-        .map(|(_, v)| v)
-        .fold(0, |s, &&b| s << 1 | b)
-}
+type FormulaId = usize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Formula {
-    Value(String),
-    And(Box<Formula>, Box<Formula>),
-    Or(Box<Formula>, Box<Formula>),
-    Xor(Box<Formula>, Box<Formula>),
-}
-
-impl PartialEq for Formula {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::Value(l0), Self::Value(r0)) => l0 == r0,
-            (Self::And(l0, l1), Self::And(r0, r1))
-            | (Self::Or(l0, l1), Self::Or(r0, r1))
-            | (Self::Xor(l0, l1), Self::Xor(r0, r1)) => {
-                (l0 == r0 && l1 == r1) || (l0 == r1 && l1 == r0)
+    Value(usize),
+    Gate(Op, FormulaId, FormulaId),
+}
+
+// hash-consed formulas: every distinct subterm is stored once, and gate operands are kept
+// in id order, so two formulas are equivalent up to commutativity iff their ids are equal
+#[derive(Debug, Default)]
+struct FormulaDag {
+    nodes: Vec<Formula>,
+    ids: HashMap<Formula, FormulaId>,
+    names: Vec<String>,
+    name_ids: HashMap<String, usize>,
+    // formulas already built for the equations this dag is used with
+    wires: HashMap<String, FormulaId>,
+}
+
+impl FormulaDag {
+    fn intern(&mut self, node: Formula) -> FormulaId {
+        if let Some(&id) = self.ids.get(&node) {
+            return id;
+        }
+        self.nodes.push(node);
+        self.ids.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn value(&mut self, name: &str) -> FormulaId {
+        let n = match self.name_ids.get(name) {
+            Some(&n) => n,
+            None => {
+                self.names.push(name.to_string());
+                self.name_ids.insert(name.to_string(), self.names.len() - 1);
+                self.names.len() - 1
             }
-            _ => false,
+        };
+        self.intern(Formula::Value(n))
+    }
+
+    fn gate(&mut self, op: Op, a: FormulaId, b: FormulaId) -> FormulaId {
+        self.intern(Formula::Gate(op, a.min(b), a.max(b)))
+    }
+
+    fn formula_from_input(&mut self, rhs: &str, equations: &Equations) -> FormulaId {
+        if let Some(&id) = self.wires.get(rhs) {
+            return id;
         }
+        let id = match equations.get(rhs) {
+            Some((o1, op, o2)) => {
+                let o1 = self.formula_from_input(o1, equations);
+                let o2 = self.formula_from_input(o2, equations);
+                match Op::parse(op) {
+                    Ok(op) => self.gate(op, o1, o2),
+                    Err(_) => unreachable!("unknow formula: {op:?}"),
+                }
+            }
+            None => self.value(rhs),
+        };
+        self.wires.insert(rhs.to_string(), id);
+        id
+    }
+
+    fn addition(&mut self, bit: usize) -> (FormulaId, FormulaId) {
+        // carry_in, a, b
+        // sum = (carry in) xor (a xor b)
+        // carry_out = (carry in) and (a xor b) or (a and b)
+        // z0 = x0 xor y0
+        // carry = x0 and y0
+        let mut carry: Option<FormulaId> = None;
+        let mut sum = 0;
+        for i in 0..=bit {
+            let x = self.value(&format!("x{i:02}"));
+            let y = self.value(&format!("y{i:02}"));
+            let xor = self.gate(Op::Xor, x, y);
+            let and = self.gate(Op::And, x, y);
+            (sum, carry) = match carry {
+                None => (xor, Some(and)),
+                Some(c) => {
+                    let propagate = self.gate(Op::And, xor, c);
+                    (
+                        self.gate(Op::Xor, c, xor),
+                        Some(self.gate(Op::Or, and, propagate)),
+                    )
+                }
+            };
+        }
+        (sum, carry.unwrap())
+    }
+
+    fn expected(&mut self, bits: usize) -> Vec<FormulaId> {
+        // z0..z(n-1) are sum bits, zn is the carry out of the last full adder
+        let mut formulas: Vec<_> = (0..bits).map(|i| self.addition(i).0).collect();
+        if bits > 0 {
+            formulas.push(self.addition(bits - 1).1);
+        }
+        formulas
+    }
+
+    fn pretty(&self, id: FormulaId) -> String {
+        // gates over two plain wires stay inline, deeper subterms get a numbered line each
+        fn term(
+            dag: &FormulaDag,
+            id: FormulaId,
+            top: bool,
+            bound: &mut HashMap<FormulaId, String>,
+            lines: &mut Vec<String>,
+        ) -> String {
+            let (op, a, b) = match dag.nodes[id] {
+                Formula::Value(n) => return dag.names[n].clone(),
+                Formula::Gate(op, a, b) => (op, a, b),
+            };
+            if let Some(t) = bound.get(&id) {
+                return t.clone();
+            }
+            let expr = format!(
+                "{} {} {}",
+                term(dag, a, false, bound, lines),
+                op.symbol(),
+                term(dag, b, false, bound, lines)
+            );
+            let leaves = [a, b]
+                .iter()
+                .all(|&c| matches!(dag.nodes[c], Formula::Value(_)));
+            if top {
+                expr
+            } else if leaves {
+                format!("({expr})")
+            } else {
+                let t = format!("t{}", bound.len());
+                lines.push(format!("{t} = {expr}"));
+                bound.insert(id, t.clone());
+                t
+            }
+        }
+        let mut lines = vec![];
+        let top = term(self, id, true, &mut HashMap::new(), &mut lines);
+        lines.push(top);
+        lines.join("\n")
     }
 }
 
-impl Formula {
-    #[allow(dead_code)]
-    fn eval(&self, wires: &HashMap<String, usize>) -> usize {
-        match self {
-            Formula::Value(w) => *wires.get(w).unwrap(),
-            Formula::And(formula, formula1) => formula.eval(wires) & formula1.eval(wires),
-            Formula::Or(formula, formula1) => formula.eval(wires) | formula1.eval(wires),
-            Formula::Xor(formula, formula1) => formula.eval(wires) ^ formula1.eval(wires),
+fn equations_from_gates(gates: &[Gate]) -> Equations {
+    gates
+        .iter()
+        .cloned()
+        .map(|[o1, op, o2, rhs]| (rhs, (o1, op, o2)))
+        .collect()
+}
+
+fn swap_outputs(gates: &mut [Gate], a: &str, b: &str) {
+    for gate in gates.iter_mut() {
+        if gate[3] == a {
+            gate[3] = b.to_string();
+        } else if gate[3] == b {
+            gate[3] = a.to_string();
         }
     }
 }
 
-fn formula_from_input(rhs: &str, equations: &HashMap<String, (String, String, String)>) -> Formula {
-    use Formula::*;
-    if let Some(ops) = equations.get(rhs) {
-        let o1 = Box::new(formula_from_input(&ops.0, equations));
-        let o2 = Box::new(formula_from_input(&ops.2, equations));
-        match ops.1.as_str() {
-            "AND" => And(o1, o2),
-            "XOR" => Xor(o1, o2),
-            "OR" => Or(o1, o2),
-            _ => unreachable!("unknow formula: {ops:?}"),
+fn is_acyclic(equations: &Equations) -> bool {
+    // 0: unvisited, 1: on stack, 2: done
+    fn dfs<'a>(w: &'a str, equations: &'a Equations, state: &mut HashMap<&'a str, u8>) -> bool {
+        match state.get(w) {
+            Some(1) => return false,
+            Some(_) => return true,
+            None => (),
+        }
+        if let Some((o1, _, o2)) = equations.get(w) {
+            state.insert(w, 1);
+            if !dfs(o1, equations, state) || !dfs(o2, equations, state) {
+                return false;
+            }
         }
-    } else {
-        Value(rhs.to_string())
+        state.insert(w, 2);
+        true
     }
+    let mut state = HashMap::new();
+    equations.keys().all(|w| dfs(w, equations, &mut state))
 }
 
-fn addition_formula(rhs: usize) -> (Formula, Formula) {
-    // carry_in, a, b
-    // sum = (carry in) xor (a xor b)
-    // carry_out = (carry in) and (a xor b) or (a and b)
-    // z0 = x0 xor y0
-    // carry = x0 and y0
-    // z1 = (x0 and y0) xor (x1 xor y1)
+fn bit_index(wire: &str) -> Option<usize> {
+    wire.get(1..)?.parse().ok()
+}
+
+fn first_wrong_bit(equations: &Equations, bits: usize) -> Option<usize> {
+    let mut dag = FormulaDag::default();
+    let expected = dag.expected(bits);
+    (0..expected.len())
+        .find(|&i| dag.formula_from_input(&format!("z{i:02}"), equations) != expected[i])
+}
 
-    use Formula::*;
-    let x = Box::new(Value(format!("x{rhs:02}")));
-    let y = Box::new(Value(format!("y{rhs:02}")));
-    if rhs == 0 {
-        (Xor(x.clone(), y.clone()), And(x.clone(), y.clone()))
-    } else {
-        let (_, c) = addition_formula(rhs - 1);
-        let xor = Box::new(Xor(x.clone(), y.clone()));
-        let and = Box::new(And(x.clone(), y.clone()));
-        (
-            Xor(Box::new(c.clone()), xor.clone()),
-            Or(and, Box::new(And(xor, Box::new(c)))),
-        )
+fn bit_labels(equations: &Equations) -> HashMap<String, usize> {
+    // label of a gate is the highest x/y bit feeding it, which is the full adder it belongs to
+    fn label(w: &str, equations: &Equations, labels: &mut HashMap<String, usize>) -> usize {
+        if let Some(&l) = labels.get(w) {
+            return l;
+        }
+        let l = match equations.get(w) {
+            Some((o1, _, o2)) => label(o1, equations, labels).max(label(o2, equations, labels)),
+            None => bit_index(w).unwrap_or(0),
+        };
+        labels.insert(w.to_string(), l);
+        l
     }
+    let mut labels = HashMap::new();
+    for w in equations.keys() {
+        label(w, equations, &mut labels);
+    }
+    labels.retain(|w, _| equations.contains_key(w));
+    labels
 }
 
-fn part2(wires: &HashMap<String, usize>, gates: &[[String; 4]]) -> Result<String> {
-    let _start = Instant::now();
+fn xorshift(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
 
-    let x = dec(wires, "x");
-    let y = dec(wires, "y");
-    let expected_z = x + y;
-    let wrong_z = run_circuit(wires, gates);
+fn random_check(
+    wires: &HashMap<String, usize>,
+    gates: &[Gate],
+    upto: usize,
+    rng: &mut u64,
+) -> bool {
+    let Ok(circuit) = Circuit::compile(wires, gates) else {
+        return false;
+    };
+    let bits = circuit.x.len();
+    let upto = upto.min(bits + 1);
+    (0..16).all(|_| {
+        let x: Vec<_> = (0..bits).map(|_| (xorshift(rng) & 1) as u8).collect();
+        let y: Vec<_> = (0..bits).map(|_| (xorshift(rng) & 1) as u8).collect();
+        let mut carry = 0;
+        let mut sum = vec![];
+        for i in 0..bits {
+            sum.push(x[i] ^ y[i] ^ carry);
+            carry = (x[i] & y[i]) | (carry & (x[i] ^ y[i]));
+        }
+        sum.push(carry);
+        circuit.add_bits(&x, &y).get(..upto) == Some(&sum[..upto])
+    })
+}
 
-    println!("{x} + {y} = {expected_z}");
-    println!("correct: {expected_z:0b}");
-    println!("wrong  : {wrong_z:0b}");
+fn best_swap(
+    gates: &[Gate],
+    candidates: &[(String, String)],
+    wires: &HashMap<String, usize>,
+    wrong_bit: usize,
+    bits: usize,
+    rng: &mut u64,
+) -> Option<(usize, (String, String))> {
+    let mut best: Option<(usize, (String, String))> = None;
+    for (a, b) in candidates {
+        let mut gates = gates.to_vec();
+        swap_outputs(&mut gates, a, b);
+        let equations = equations_from_gates(&gates);
+        if !is_acyclic(&equations) {
+            continue;
+        }
+        let reached = first_wrong_bit(&equations, bits).unwrap_or(bits + 1);
+        if reached <= wrong_bit || best.as_ref().is_some_and(|(r, _)| *r >= reached) {
+            continue;
+        }
+        if random_check(wires, &gates, reached, rng) {
+            best = Some((reached, (a.clone(), b.clone())));
+        }
+    }
+    best
+}
 
-    let equations: HashMap<_, _> = gates
-        .iter()
-        .cloned()
-        .map(|[o1, op, o2, rhs]| (rhs, (o1, op, o2)))
-        .collect();
+fn repair_circuit(
+    wires: &HashMap<String, usize>,
+    gates: &[Gate],
+    max_swaps: usize,
+) -> Result<Vec<String>> {
+    let bits = bus_width(wires.keys(), "x")?;
+    let mut gates = gates.to_vec();
+    let mut swapped = vec![];
+    let mut rng = 0x2024_1224_u64;
+
+    if !is_acyclic(&equations_from_gates(&gates)) {
+        return err!("circuit has a cycle");
+    }
+
+    while let Some(wrong_bit) = first_wrong_bit(&equations_from_gates(&gates), bits) {
+        if swapped.len() == max_swaps * 2 {
+            return err!("still wrong at bit {wrong_bit} after {max_swaps} swaps");
+        }
+        let labels = bit_labels(&equations_from_gates(&gates));
+        let mut outputs: Vec<_> = labels.keys().cloned().collect();
+        outputs.sort();
+        let local: Vec<_> = outputs
+            .iter()
+            .filter(|w| labels[*w] + 1 >= wrong_bit && labels[*w] <= wrong_bit)
+            .cloned()
+            .collect();
 
-    for i in 0..wires.len() / 2 {
-        let rhs = format!("z{i:02}");
-        let input_formula = formula_from_input(&rhs, &equations);
-        let true_formula = addition_formula(i).0;
-        if input_formula != true_formula {
-            println!("different at bit: {i}, try to fix it by hand, then re-run");
-            // println!("{:?}", input_formula);
-            // println!("{:?}", true_formula);
-            break;
+        // swaps inside the broken full adder first, then anything touching it
+        let pairs: Vec<_> = local
+            .iter()
+            .tuple_combinations()
+            .map(|(a, b)| (a.clone(), b.clone()))
+            .collect();
+        let mut found = best_swap(&gates, &pairs, wires, wrong_bit, bits, &mut rng);
+        if found.is_none() {
+            let pairs: Vec<_> = local
+                .iter()
+                .cartesian_product(outputs.iter().filter(|w| !local.contains(w)))
+                .map(|(a, b)| (a.clone(), b.clone()))
+                .collect();
+            found = best_swap(&gates, &pairs, wires, wrong_bit, bits, &mut rng);
         }
+        let Some((_, (a, b))) = found else {
+            let mut dag = FormulaDag::default();
+            let expected = dag.expected(bits)[wrong_bit];
+            let actual =
+                dag.formula_from_input(&format!("z{wrong_bit:02}"), &equations_from_gates(&gates));
+            println!("expected:\n{}", dag.pretty(expected));
+            println!("circuit:\n{}", dag.pretty(actual));
+            return err!("unable to find a swap that fixes bit {wrong_bit}");
+        };
+        swap_outputs(&mut gates, &a, &b);
+        swapped.push(a);
+        swapped.push(b);
+    }
+
+    if !random_check(wires, &gates, bits + 1, &mut rng) {
+        return err!("repaired circuit is still not an adder");
     }
+    swapped.sort();
+    Ok(swapped)
+}
+
+fn part2(wires: &HashMap<String, usize>, gates: &[Gate]) -> Result<String> {
+    let _start = Instant::now();
 
-    assert_eq!(expected_z, run_circuit(wires, gates));
+    let x = decode(&decode_bus(wires, "x")?)?;
+    let y = decode(&decode_bus(wires, "y")?)?;
+    let Some(sum) = x.checked_add(y) else {
+        return err!("{x} + {y} does not fit in u128");
+    };
+    println!("{x} + {y} = {sum}");
+    println!(
+        "circuit gives: {}",
+        Circuit::compile(wires, gates)?.add(x, y)?
+    );
 
-    let result = String::new();
+    let result = repair_circuit(wires, gates, 4)?.join(",");
     println!("part2: {result}");
     println!("> Time elapsed is: {:?}", _start.elapsed());
     Ok(result)
 }
 
+fn divergent_gates(equations: &Equations, bits: usize) -> HashSet<String> {
+    // walk both formulas together and stop at the gate whose own shape is wrong
+    fn descend(
+        wire: &str,
+        expected: FormulaId,
+        equations: &Equations,
+        dag: &mut FormulaDag,
+        found: &mut HashSet<String>,
+    ) -> bool {
+        let Some((o1, op, o2)) = equations.get(wire) else {
+            return false;
+        };
+        let (e1, e2) = match (Op::parse(op), dag.nodes[expected]) {
+            (Ok(op), Formula::Gate(e, e1, e2)) if op == e => (e1, e2),
+            _ => {
+                found.insert(wire.to_string());
+                return true;
+            }
+        };
+        let f1 = dag.formula_from_input(o1, equations);
+        let f2 = dag.formula_from_input(o2, equations);
+        let marked = if f1 == e1 {
+            descend(o2, e2, equations, dag, found)
+        } else if f1 == e2 {
+            descend(o2, e1, equations, dag, found)
+        } else if f2 == e1 {
+            descend(o1, e2, equations, dag, found)
+        } else if f2 == e2 {
+            descend(o1, e1, equations, dag, found)
+        } else {
+            false
+        };
+        if !marked {
+            found.insert(wire.to_string());
+        }
+        true
+    }
+
+    let mut found = HashSet::new();
+    if !is_acyclic(equations) {
+        return found;
+    }
+    let mut dag = FormulaDag::default();
+    for (i, formula) in dag.expected(bits).into_iter().enumerate() {
+        let z = format!("z{i:02}");
+        if dag.formula_from_input(&z, equations) != formula {
+            descend(&z, formula, equations, &mut dag, &mut found);
+        }
+    }
+    found
+}
+
+fn netlist_clusters(gates: &[Gate]) -> Vec<(usize, Vec<String>)> {
+    let labels = bit_labels(&equations_from_gates(gates));
+    let mut clusters: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for [o1, _, o2, rhs] in gates {
+        for w in [o1, o2] {
+            if !labels.contains_key(w) {
+                clusters
+                    .entry(bit_index(w).unwrap_or(0))
+                    .or_default()
+                    .push(w.clone());
+            }
+        }
+        clusters.entry(labels[rhs]).or_default().push(rhs.clone());
+    }
+    clusters
+        .into_iter()
+        .map(|(bit, mut nodes)| {
+            nodes.sort();
+            nodes.dedup();
+            (bit, nodes)
+        })
+        .collect()
+}
+
+fn gate_color(op: &str) -> &'static str {
+    match op {
+        "AND" => "#8ecae6",
+        "OR" => "#b7e4c7",
+        "XOR" => "#ffb703",
+        _ => unreachable!("unknow gate: {:?}", op),
+    }
+}
+
+fn to_dot(gates: &[Gate], highlight: &HashSet<String>) -> String {
+    let ops: HashMap<_, _> = gates.iter().map(|g| (&g[3], &g[1])).collect();
+    let mut dot = String::from("digraph circuit {\n    rankdir=LR;\n    node [style=filled];\n");
+    for (bit, nodes) in netlist_clusters(gates) {
+        dot.push_str(&format!(
+            "    subgraph cluster_{bit:02} {{\n        label=\"bit {bit}\";\n"
+        ));
+        for w in nodes {
+            let attrs = match ops.get(&w) {
+                Some(op) if highlight.contains(&w) => {
+                    format!(
+                        "label=\"{op}\\n{w}\", fillcolor=\"{}\", color=red, penwidth=3",
+                        gate_color(op)
+                    )
+                }
+                Some(op) => format!("label=\"{op}\\n{w}\", fillcolor=\"{}\"", gate_color(op)),
+                None => "shape=box, fillcolor=white".to_string(),
+            };
+            dot.push_str(&format!("        {w} [{attrs}];\n"));
+        }
+        dot.push_str("    }\n");
+    }
+    for [o1, _, o2, rhs] in gates {
+        dot.push_str(&format!("    {o1} -> {rhs};\n    {o2} -> {rhs};\n"));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn to_mermaid(gates: &[Gate], highlight: &HashSet<String>) -> String {
+    let ops: HashMap<_, _> = gates.iter().map(|g| (&g[3], &g[1])).collect();
+    let mut mermaid = String::from("flowchart LR\n");
+    for op in ["AND", "OR", "XOR"] {
+        mermaid.push_str(&format!(
+            "    classDef {} fill:{}\n",
+            op.to_lowercase(),
+            gate_color(op)
+        ));
+    }
+    mermaid.push_str("    classDef diverge stroke:#f00,stroke-width:3px\n");
+    for (bit, nodes) in netlist_clusters(gates) {
+        mermaid.push_str(&format!("    subgraph bit{bit:02}\n"));
+        for w in nodes {
+            match ops.get(&w) {
+                Some(op) => mermaid.push_str(&format!(
+                    "        {w}[\"{op} {w}\"]:::{}\n",
+                    op.to_lowercase()
+                )),
+                None => mermaid.push_str(&format!("        {w}[/{w}/]\n")),
+            }
+        }
+        mermaid.push_str("    end\n");
+    }
+    for [o1, _, o2, rhs] in gates {
+        mermaid.push_str(&format!("    {o1} --> {rhs}\n    {o2} --> {rhs}\n"));
+    }
+    let mut highlight: Vec<_> = highlight.iter().cloned().collect();
+    highlight.sort();
+    if !highlight.is_empty() {
+        mermaid.push_str(&format!("    class {} diverge\n", highlight.join(",")));
+    }
+    mermaid
+}
+
+fn export_circuit(wires: &HashMap<String, usize>, gates: &[Gate], args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        return Ok(());
+    }
+    let highlight = divergent_gates(&equations_from_gates(gates), bus_width(wires.keys(), "x")?);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(path) = args.next() else {
+            return err!("missing output path for {arg}");
+        };
+        let output = match arg.as_str() {
+            "--dot" => to_dot(gates, &highlight),
+            "--mermaid" => to_mermaid(gates, &highlight),
+            _ => return err!("unknown argument: {arg}"),
+        };
+        std::fs::write(path, output)?;
+        println!("circuit written to {path}");
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
     let (wires, gates) = parse_input(input)?;
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    export_circuit(&wires, &gates, &args)?;
     part1(&wires, &gates)?;
     part2(&wires, &gates)?;
     Ok(())
@@ -277,3 +914,152 @@ fn real_input() -> Result<()> {
     assert_eq!(part1(&wires, &gates)?, 52038112429798);
     Ok(())
 }
+
+#[cfg(test)]
+fn ripple_carry_adder(bits: usize) -> Vec<Gate> {
+    let gate = |o1: String, op: &str, o2: String, rhs: String| [o1, op.to_string(), o2, rhs];
+    let mut gates = vec![];
+    for i in 0..bits {
+        let (x, y) = (format!("x{i:02}"), format!("y{i:02}"));
+        let carry_out = if i + 1 == bits {
+            format!("z{bits:02}")
+        } else {
+            format!("c{i:02}")
+        };
+        if i == 0 {
+            gates.push(gate(x.clone(), "XOR", y.clone(), "z00".to_string()));
+            gates.push(gate(x, "AND", y, carry_out));
+            continue;
+        }
+        let carry_in = format!("c{:02}", i - 1);
+        gates.push(gate(x.clone(), "XOR", y.clone(), format!("p{i:02}")));
+        gates.push(gate(y, "AND", x, format!("g{i:02}")));
+        gates.push(gate(
+            carry_in.clone(),
+            "XOR",
+            format!("p{i:02}"),
+            format!("z{i:02}"),
+        ));
+        gates.push(gate(
+            format!("p{i:02}"),
+            "AND",
+            carry_in,
+            format!("t{i:02}"),
+        ));
+        gates.push(gate(
+            format!("g{i:02}"),
+            "OR",
+            format!("t{i:02}"),
+            carry_out,
+        ));
+    }
+    gates
+}
+
+#[test]
+fn repair_swapped_adder() -> Result<()> {
+    let bits = 12;
+    let mut gates = ripple_carry_adder(bits);
+    let mut wires = HashMap::new();
+    for i in 0..bits {
+        wires.insert(format!("x{i:02}"), 1);
+        wires.insert(format!("y{i:02}"), i % 2);
+    }
+    assert_eq!(part2(&wires, &gates)?, "");
+
+    swap_outputs(&mut gates, "z03", "c03");
+    swap_outputs(&mut gates, "p07", "g07");
+    swap_outputs(&mut gates, "z10", "t10");
+    assert_eq!(part2(&wires, &gates)?, "c03,g07,p07,t10,z03,z10");
+    Ok(())
+}
+
+#[test]
+fn export_highlights_divergence() -> Result<()> {
+    let bits = 8;
+    let mut gates = ripple_carry_adder(bits);
+    assert!(divergent_gates(&equations_from_gates(&gates), bits).is_empty());
+
+    swap_outputs(&mut gates, "z03", "c03");
+    let highlight = divergent_gates(&equations_from_gates(&gates), bits);
+    assert_eq!(
+        highlight,
+        HashSet::from(["z03".to_string(), "c03".to_string()])
+    );
+
+    let dot = to_dot(&gates, &highlight);
+    assert!(dot.contains("subgraph cluster_03"));
+    assert!(
+        dot.contains("z03 [label=\"OR\\nz03\", fillcolor=\"#b7e4c7\", color=red, penwidth=3];")
+    );
+    let mermaid = to_mermaid(&gates, &highlight);
+    assert!(mermaid.contains("    class c03,z03 diverge\n"));
+    Ok(())
+}
+
+#[test]
+fn compile_rejects_bad_netlists() -> Result<()> {
+    let (wires, gates) = parse_input("x00: 1\ny00: 0\n\nx00 XOR y00 -> z00\nx00 AND y00 -> z01")?;
+    let circuit = Circuit::compile(&wires, &gates)?;
+    assert_eq!(circuit.evaluate(&wires), vec![1, 0]);
+    assert_eq!(circuit.add(1, 1)?, 0b10);
+
+    let (wires, gates) = parse_input("x00: 1\ny00: 0\n\nx00 XOR abc -> z00\nz00 AND y00 -> abc")?;
+    let e = Circuit::compile(&wires, &gates).unwrap_err();
+    assert!(e.to_string().starts_with("circuit has a cycle"));
+
+    let (wires, gates) = parse_input("x00: 1\ny00: 0\n\nx00 XOR abc -> z00")?;
+    let e = Circuit::compile(&wires, &gates).unwrap_err();
+    assert_eq!(e.to_string(), "undriven wires: abc");
+    Ok(())
+}
+
+#[test]
+fn wide_bus_io() -> Result<()> {
+    let bits = 100;
+    let gates = ripple_carry_adder(bits);
+    let (x, y) = (u128::MAX >> 29, (1 << 99) + 12345);
+    let mut wires = HashMap::new();
+    encode_bus(&mut wires, "x", &encode(x, bits));
+    encode_bus(&mut wires, "y", &encode(y, bits));
+    assert_eq!(decode(&decode_bus(&wires, "x")?)?, x);
+    assert_eq!(part1(&wires, &gates)?, x + y);
+    assert_eq!(Circuit::compile(&wires, &gates)?.add(y, y)?, y + y);
+
+    // a full 128 bit bus has no room for the carry
+    let mut wires = HashMap::new();
+    encode_bus(&mut wires, "x", &encode(u128::MAX, 128));
+    encode_bus(&mut wires, "y", &encode(1, 128));
+    assert!(part2(&wires, &ripple_carry_adder(128)).is_err());
+
+    assert!(decode(&encode(1, 200)).is_ok());
+    assert!(decode(&[vec![0; 150], vec![1]].concat()).is_err());
+    assert!(parse_input("x00: 1\nx02: 0\n\nx00 XOR x02 -> z00").is_err());
+    Ok(())
+}
+
+#[test]
+fn formula_dag_equivalence() -> Result<()> {
+    let bits = 45;
+    let equations = equations_from_gates(&ripple_carry_adder(bits));
+    let mut dag = FormulaDag::default();
+    let expected = dag.expected(bits);
+    for (i, &formula) in expected.iter().enumerate() {
+        assert_eq!(
+            dag.formula_from_input(&format!("z{i:02}"), &equations),
+            formula
+        );
+    }
+    // each full adder adds five gates on top of the shared carry chain
+    assert!(dag.nodes.len() < 8 * bits);
+
+    let (x, y) = (dag.value("x00"), dag.value("y00"));
+    assert_eq!(dag.gate(Op::And, x, y), dag.gate(Op::And, y, x));
+    assert_ne!(dag.gate(Op::And, x, y), dag.gate(Op::Or, x, y));
+    assert_eq!(dag.pretty(expected[0]), "x00 ^ y00");
+    assert_eq!(
+        dag.pretty(expected[2]),
+        "t0 = (x00 & y00) & (x01 ^ y01)\nt1 = (x01 & y01) | t0\nt1 ^ (x02 ^ y02)"
+    );
+    Ok(())
+}